@@ -2,7 +2,7 @@
 //! Chinese Input Debug Test Example
 
 use bevy::{
-    color::palettes::css::{DARK_BLUE, LIGHT_BLUE, NAVY, WHITE, YELLOW},
+    color::palettes::css::{LIGHT_BLUE, WHITE, YELLOW},
     prelude::*,
     window::Ime,
     winit::WinitWindows,
@@ -166,8 +166,8 @@ fn debug_focus_system(
         info!("🎯 Focus changed: {:?}", focus.get());
         
         // Manually enable IME if focus changed to a text input
-        if !*manual_ime_enabled {
-            if let Some(winit_windows) = winit_windows {
+        if !*manual_ime_enabled
+            && let Some(winit_windows) = winit_windows {
                 for window_entity in windows.iter() {
                     if let Some(winit_window) = winit_windows.get_window(window_entity) {
                         info!("🔧 Manually enabling IME due to focus change");
@@ -177,7 +177,6 @@ fn debug_focus_system(
                     }
                 }
             }
-        }
     }
 }
 
@@ -0,0 +1,81 @@
+//! find and replace example
+//! Press F1 to find "fox", F2 and F3 to select the next and previous matches,
+//! F4 to replace the selected match with "cat" and F5 to replace every match.
+//! Press F6 to end the search.
+
+use bevy::{color::palettes::css::NAVY, prelude::*};
+use bevy_ui_text_input::{TextInputNode, TextInputPlugin, TextInputQueue, search::SearchOptions};
+
+fn main() {
+    App::new()
+        .add_plugins((DefaultPlugins, TextInputPlugin))
+        .add_systems(Startup, setup)
+        .add_systems(Update, find_and_replace)
+        .run();
+}
+
+fn setup(mut commands: Commands, assets: Res<AssetServer>) {
+    // UI camera
+    commands.spawn(Camera2d);
+
+    let mut queue = TextInputQueue::default();
+    queue.set_text(
+        "The quick brown fox jumps over the lazy dog.\nA fox is a fox, and Fox is a fox too.\nFoxes are not a fox.",
+    );
+
+    commands
+        .spawn(Node {
+            width: Val::Percent(100.),
+            height: Val::Percent(100.),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..Default::default()
+        })
+        .with_child((
+            TextInputNode {
+                clear_on_submit: false,
+                ..Default::default()
+            },
+            queue,
+            TextFont {
+                font: assets.load("fonts/FiraMono-Medium.ttf"),
+                font_size: 25.,
+                ..Default::default()
+            },
+            Node {
+                width: Val::Px(500.),
+                height: Val::Px(300.),
+                ..default()
+            },
+            BackgroundColor(NAVY.into()),
+        ));
+}
+
+fn find_and_replace(keyboard: Res<ButtonInput<KeyCode>>, mut query: Query<&mut TextInputQueue>) {
+    for mut queue in query.iter_mut() {
+        if keyboard.just_pressed(KeyCode::F1) {
+            queue.find(
+                "fox",
+                SearchOptions {
+                    whole_word: true,
+                    ..Default::default()
+                },
+            );
+        }
+        if keyboard.just_pressed(KeyCode::F2) {
+            queue.find_next();
+        }
+        if keyboard.just_pressed(KeyCode::F3) {
+            queue.find_previous();
+        }
+        if keyboard.just_pressed(KeyCode::F4) {
+            queue.replace("cat");
+        }
+        if keyboard.just_pressed(KeyCode::F5) {
+            queue.replace_all("cat");
+        }
+        if keyboard.just_pressed(KeyCode::F6) {
+            queue.clear_search();
+        }
+    }
+}
@@ -2,7 +2,7 @@
 use bevy::prelude::*;
 use bevy_ui_text_input::{
     TextInputBuffer, TextInputNode, TextInputPlugin, TextInputQueue,
-    TextInputStyle, actions::TextInputAction, actions::TextInputEdit,
+    TextInputStyle,
 };
 
 fn main() {
@@ -108,6 +108,7 @@ fn debug_input_focus(
     }
 }
 
+#[allow(clippy::type_complexity)]
 fn debug_queue_processing(
     query: Query<(&TextInputQueue, Entity), (With<DebugMarker>, Changed<TextInputQueue>)>,
 ) {
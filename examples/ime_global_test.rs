@@ -55,12 +55,12 @@ fn global_ime_handler(
                 info!("✅ Global handler: IME Commit '{}'", value);
                 
                 // Try to insert text manually
-                if let Some(focused) = input_focus.get() {
-                    if let Ok(mut queue) = text_inputs.get_mut(focused) {
-                        info!("  Inserting into focused entity {:?}", focused);
-                        for ch in value.chars() {
-                            queue.add(TextInputAction::Edit(TextInputEdit::Insert(ch, false)));
-                        }
+                if let Some(focused) = input_focus.get()
+                    && let Ok(mut queue) = text_inputs.get_mut(focused)
+                {
+                    info!("  Inserting into focused entity {:?}", focused);
+                    for ch in value.chars() {
+                        queue.add(TextInputAction::Edit(TextInputEdit::Insert(ch, false)));
                     }
                 }
             }
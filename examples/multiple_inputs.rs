@@ -2,7 +2,10 @@
 
 use bevy::{
     color::palettes::css::{GREY, LIGHT_GOLDENROD_YELLOW},
-    input_focus::InputFocus,
+    input_focus::{
+        InputFocus,
+        tab_navigation::{TabGroup, TabIndex},
+    },
     platform::collections::HashMap,
     prelude::*,
 };
@@ -33,6 +36,17 @@ fn setup(mut commands: Commands, assets: Res<AssetServer>) {
         (Some(TextInputFilter::Integer), "integer"),
         (Some(TextInputFilter::Decimal), "decimal"),
         (Some(TextInputFilter::Hex), "hex"),
+        (Some(TextInputFilter::Identifier), "identifier"),
+        (Some(TextInputFilter::Email), "email"),
+        (
+            Some(TextInputFilter::custom(|text| {
+                text.split('.').count() <= 4
+                    && text
+                        .split('.')
+                        .all(|part| part.is_empty() || part.parse::<u8>().is_ok())
+            })),
+            "ipv4 address",
+        ),
     ];
 
     commands
@@ -46,13 +60,16 @@ fn setup(mut commands: Commands, assets: Res<AssetServer>) {
         })
         .with_children(|commands| {
             commands
-                .spawn(Node {
-                    display: Display::Grid,
-                    grid_template_columns: vec![GridTrack::auto(), GridTrack::px(300.)],
-                    column_gap: Val::Px(20.),
-                    row_gap: Val::Px(20.),
-                    ..Default::default()
-                })
+                .spawn((
+                    Node {
+                        display: Display::Grid,
+                        grid_template_columns: vec![GridTrack::auto(), GridTrack::px(300.)],
+                        column_gap: Val::Px(20.),
+                        row_gap: Val::Px(20.),
+                        ..Default::default()
+                    },
+                    TabGroup::new(0),
+                ))
                 .with_children(|commands| {
                     for (filter, prompt) in filters {
                         let input_entity = commands
@@ -61,8 +78,11 @@ fn setup(mut commands: Commands, assets: Res<AssetServer>) {
                                     mode: TextInputMode::SingleLine,
                                     filter,
                                     max_chars: Some(20),
+                                    tab_navigation: true,
+                                    select_all_on_focus: true,
                                     ..Default::default()
                                 },
+                                TabIndex(0),
                                 TextFont {
                                     font: assets.load("fonts/FiraMono-Medium.ttf"),
                                     font_size: 25.,
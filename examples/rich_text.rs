@@ -0,0 +1,120 @@
+//! rich text example
+//! Press F1 to make the selected text red and F2 to underline it.
+//! Press F3 to remove the styling from the selected text.
+
+use bevy::{
+    color::palettes::css::{GOLD, NAVY, RED, SKY_BLUE},
+    prelude::*,
+    text::cosmic_text::Edit,
+};
+use bevy_ui_text_input::{
+    TextInputBuffer, TextInputNode, TextInputPlugin, TextInputQueue, spans::TextInputSpanStyle,
+};
+
+fn main() {
+    App::new()
+        .add_plugins((DefaultPlugins, TextInputPlugin))
+        .add_systems(Startup, setup)
+        .add_systems(Update, style_selection)
+        .run();
+}
+
+fn setup(mut commands: Commands, assets: Res<AssetServer>) {
+    // UI camera
+    commands.spawn(Camera2d);
+
+    let text = "Hello @everyone, the bold text is important.\nThis is wrong and struck out, this is underlined and this is big.";
+    let style_of = |word: &str| {
+        let start = text.find(word).unwrap();
+        start..start + word.len()
+    };
+
+    let mut queue = TextInputQueue::default();
+    queue.set_text(text);
+    queue.set_style(
+        style_of("@everyone"),
+        TextInputSpanStyle::default().with_color(SKY_BLUE),
+    );
+    queue.set_style(
+        style_of("bold text"),
+        TextInputSpanStyle::default().with_font(assets.load("fonts/FiraSans-Bold.ttf")),
+    );
+    queue.set_style(
+        style_of("wrong"),
+        TextInputSpanStyle::default()
+            .with_color(RED)
+            .strikethrough(),
+    );
+    queue.set_style(
+        style_of("underlined"),
+        TextInputSpanStyle::default().underline(),
+    );
+    queue.set_style(
+        style_of("big"),
+        TextInputSpanStyle::default()
+            .with_font_size(40.)
+            .with_color(GOLD),
+    );
+
+    commands
+        .spawn(Node {
+            width: Val::Percent(100.),
+            height: Val::Percent(100.),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..Default::default()
+        })
+        .with_child((
+            TextInputNode {
+                clear_on_submit: false,
+                ..Default::default()
+            },
+            queue,
+            TextFont {
+                font: assets.load("fonts/FiraMono-Medium.ttf"),
+                font_size: 25.,
+                ..Default::default()
+            },
+            Node {
+                width: Val::Px(500.),
+                height: Val::Px(300.),
+                ..default()
+            },
+            BackgroundColor(NAVY.into()),
+        ));
+}
+
+fn style_selection(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut query: Query<(&TextInputBuffer, &mut TextInputQueue)>,
+) {
+    for (buffer, mut queue) in query.iter_mut() {
+        let Some(range) = selected_range(buffer) else {
+            continue;
+        };
+        if keyboard.just_pressed(KeyCode::F1) {
+            queue.set_style(range.clone(), TextInputSpanStyle::default().with_color(RED));
+        }
+        if keyboard.just_pressed(KeyCode::F2) {
+            queue.set_style(range.clone(), TextInputSpanStyle::default().underline());
+        }
+        if keyboard.just_pressed(KeyCode::F3) {
+            queue.clear_style(range);
+        }
+    }
+}
+
+/// Byte range of the selected text
+fn selected_range(buffer: &TextInputBuffer) -> Option<std::ops::Range<usize>> {
+    let (start, end) = buffer.editor.selection_bounds()?;
+    buffer.editor.with_buffer(|text_buffer| {
+        let offset = |line: usize, index: usize| {
+            text_buffer.lines[..line]
+                .iter()
+                .map(|line| line.text().len() + 1)
+                .sum::<usize>()
+                + index
+        };
+        Some(offset(start.line, start.index)..offset(end.line, end.index))
+    })
+}
@@ -0,0 +1,55 @@
+//! syntax highlighting example
+//! Edits RON with the built-in `KeywordHighlighter` and a line number gutter.
+
+use bevy::{color::palettes::css::NAVY, prelude::*};
+use bevy_ui_text_input::{
+    TextInputNode, TextInputPlugin, TextInputQueue,
+    gutter::TextInputGutter,
+    highlight::{KeywordHighlighter, TextInputHighlight},
+};
+
+fn main() {
+    App::new()
+        .add_plugins((DefaultPlugins, TextInputPlugin))
+        .add_systems(Startup, setup)
+        .run();
+}
+
+fn setup(mut commands: Commands, assets: Res<AssetServer>) {
+    // UI camera
+    commands.spawn(Camera2d);
+
+    let mut queue = TextInputQueue::default();
+    queue.set_text(
+        "// player settings\nPlayer(\n    name: \"Ferris\",\n    health: 100,\n    speed: 2.5,\n    invincible: false,\n    weapon: Some(\"crab claw\"),\n    shield: None,\n)",
+    );
+
+    commands
+        .spawn(Node {
+            width: Val::Percent(100.),
+            height: Val::Percent(100.),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..Default::default()
+        })
+        .with_child((
+            TextInputNode {
+                clear_on_submit: false,
+                ..Default::default()
+            },
+            queue,
+            TextInputHighlight::new(KeywordHighlighter::ron()),
+            TextInputGutter::default(),
+            TextFont {
+                font: assets.load("fonts/FiraMono-Medium.ttf"),
+                font_size: 25.,
+                ..Default::default()
+            },
+            Node {
+                width: Val::Px(500.),
+                height: Val::Px(300.),
+                ..default()
+            },
+            BackgroundColor(NAVY.into()),
+        ));
+}
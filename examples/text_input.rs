@@ -6,7 +6,7 @@ use bevy::{
 };
 use bevy_ui_text_input::{
     TextInputQueue, TextInputBuffer, TextInputMode, TextInputNode, TextInputPlugin, TextInputPrompt,
-    TextInputStyle, TextSubmitEvent, actions::TextInputAction,
+    TextInputStyle, TextSubmitEvent, actions::TextInputAction, scrollbar::TextInputScrollbar,
 };
 
 fn main() {
@@ -84,6 +84,7 @@ fn setup(mut commands: Commands, assets: Res<AssetServer>) {
                 ..default()
             },
             TextInputStyle::default(),
+            TextInputScrollbar::default(),
             BackgroundColor(Color::srgb(0., 0., 0.2)),
         ))
         .id();
@@ -303,7 +304,7 @@ fn setup(mut commands: Commands, assets: Res<AssetServer>) {
                                     }
                                 },
                             )
-                            .with_child(Text::new(format!("wrap")));
+                            .with_child(Text::new("wrap".to_string()));
                         });
 
                         commands
@@ -334,7 +335,7 @@ fn setup(mut commands: Commands, assets: Res<AssetServer>) {
                                     }
                                 },
                             )
-                            .with_child(Text::new(format!("align")));
+                            .with_child(Text::new("align".to_string()));
                         });
                 });
         })
@@ -392,6 +393,7 @@ fn setup(mut commands: Commands, assets: Res<AssetServer>) {
         });
 }
 
+#[allow(clippy::type_complexity)]
 fn button_system(
     mut interaction_query: Query<
         (&Interaction, &mut BorderColor, &Children),
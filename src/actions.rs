@@ -1,15 +1,27 @@
+use bevy::log::warn;
+use bevy::math::Vec2;
 use bevy::text::cosmic_text::Action;
 use bevy::text::cosmic_text::BorrowedWithFontSystem;
+use bevy::text::cosmic_text::Change;
+use bevy::text::cosmic_text::Cursor;
 use bevy::text::cosmic_text::Edit;
 use bevy::text::cosmic_text::Editor;
 use bevy::text::cosmic_text::Motion;
 use bevy::text::cosmic_text::Selection;
+use std::ops::Range;
 
 use crate::TextInputFilter;
 use crate::clipboard::ClipboardRead;
 use crate::edit::apply_motion;
 use crate::edit::buffer_len;
+use crate::edit::cursor_at_byte_offset;
 use crate::edit::cursor_at_line_end;
+use crate::history::EditKind;
+use crate::history::HistoryEntry;
+use crate::history::TextInputHistory;
+use crate::search::SearchOptions;
+use crate::spans::TextInputSpanStyle;
+use crate::text_input_pipeline::hit;
 
 /// Actions that can be recieved by a text input
 #[derive(Debug)]
@@ -26,10 +38,40 @@ pub enum TextInputAction {
     PasteDeferred(ClipboardRead),
     /// A single edit action
     Edit(TextInputEdit),
+    /// Set the style of a byte range of the text, `None` removes any styling.
+    /// Text inserted later takes the style of the text before it.
+    /// Style changes aren't recorded in the undo history.
+    SetStyle {
+        range: Range<usize>,
+        style: Option<TextInputSpanStyle>,
+    },
+    /// Search the text for `query` and highlight the matches, see `TextInputBuffer::search`.
+    /// Replaces any previous search.
+    Find {
+        query: String,
+        options: SearchOptions,
+    },
+    /// Select the first match of the search after the selection or cursor
+    FindNext,
+    /// Select the last match of the search before the selection or cursor
+    FindPrevious,
+    /// Replace the selected match of the search, then select the next match.
+    /// If the selection isn't a match, only selects the next match.
+    Replace(String),
+    /// Replace every match of the search as a single edit
+    ReplaceAll(String),
+    /// End the search, removing the highlights from its matches
+    ClearSearch,
+    /// Apply a deleting edit, such as `TextInputEdit::DeleteToLineEnd`, and add the deleted text to the `TextInputKillRing`
+    Kill(TextInputEdit),
+    /// Insert the most recently killed text from the `TextInputKillRing`
+    Yank,
+    /// Directly after a yank, replace the yanked text with the previous entry of the `TextInputKillRing`
+    YankPop,
 }
 
 /// An edit to perform on a [`TextInputBuffer`](crate::TextInputBuffer)
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub enum TextInputEdit {
     /// Move the cursor with some motion
     Motion(Motion, bool),
@@ -43,26 +85,42 @@ pub enum TextInputEdit {
     Backspace,
     /// Delete text in front of cursor
     Delete,
+    /// Delete the text from the cursor to the start of its line, or the line break before the cursor if it is already at the start.
+    /// Lines end at line breaks, a wrapped line is deleted up to its line break and not to where it wraps.
+    /// Deletes the selection instead if there is one.
+    DeleteToLineStart,
+    /// Delete the text from the cursor to the end of its line, or the line break after the cursor if it is already at the end.
+    /// Lines end at line breaks, like `DeleteToLineStart`. Deletes the selection instead if there is one.
+    DeleteToLineEnd,
+    /// Delete from the cursor back to the start of the previous word, or the line break before the cursor if it is at the start of a line.
+    /// Words are found using Unicode word boundaries. Deletes the selection instead if there is one.
+    DeleteWordBackward,
+    /// Delete from the cursor to the end of the next word, or the line break after the cursor if it is at the end of a line.
+    /// Words are found using Unicode word boundaries. Deletes the selection instead if there is one.
+    DeleteWordForward,
     // Indent text (typically Tab)
     Indent,
     // Unindent text (typically Shift+Tab)
     Unindent,
-    /// Mouse click at specified position
+    /// Mouse click at a position, moving the cursor and clearing the selection.
+    /// Positions of pointer edits are relative to the top left of the visible text area in physical pixels.
     Click {
         x: i32,
         y: i32,
     },
-    /// Mouse double click at specified position
+    /// Mouse double click at a position, selecting the word under the pointer.
+    /// Dragging afterwards extends the selection by words.
     DoubleClick {
         x: i32,
         y: i32,
     },
-    /// Mouse triple click at specified position
+    /// Mouse triple click at a position, selecting the line under the pointer.
+    /// Dragging afterwards extends the selection by lines.
     TripleClick {
         x: i32,
         y: i32,
     },
-    /// Mouse drag to specified position
+    /// Mouse drag to a position, extending the selection
     Drag {
         x: i32,
         y: i32,
@@ -72,16 +130,128 @@ pub enum TextInputEdit {
         lines: i32,
     },
     Paste(String),
+    /// Replace all the text in the buffer, placing the cursor at the end.
+    ///
+    /// `SetText`, `InsertAt`, `ReplaceRange` and `ReplaceRanges` are rejected, leaving the text unchanged and logging a
+    /// warning, if the resulting text would exceed the input's `max_chars` or a range doesn't lie on char boundaries.
+    /// They are also rejected if the resulting text doesn't match the input's filter.
+    SetText(String),
+    /// Insert text at a byte offset into the buffer's text, placing the cursor after the inserted text.
+    /// Rejected like `SetText`.
+    InsertAt {
+        index: usize,
+        text: String,
+    },
+    /// Replace a byte range of the buffer's text, placing the cursor after the inserted text.
+    /// Rejected like `SetText`.
+    ReplaceRange {
+        range: Range<usize>,
+        text: String,
+    },
+    /// Replace several non-overlapping byte ranges of the buffer's text as a single edit,
+    /// placing the cursor after the last inserted text.
+    /// Rejected like `SetText`, and if any of the ranges overlap.
+    ReplaceRanges(Vec<(Range<usize>, String)>),
+    /// Delete all the text in the buffer
+    Clear,
+    /// Revert the most recent edit in the input's [`TextInputHistory`]
     Undo,
+    /// Reapply the most recently undone edit
     Redo,
     SelectAll,
+    /// Add a caret at the cursor position, see `TextInputBuffer::carets`.
+    /// Adding a caret where there already is one removes it.
+    AddCaret(Cursor),
+}
+
+impl TextInputEdit {
+    /// The position of a pointer edit
+    pub fn pointer_position(&self) -> Option<Vec2> {
+        match self {
+            TextInputEdit::Click { x, y }
+            | TextInputEdit::DoubleClick { x, y }
+            | TextInputEdit::TripleClick { x, y }
+            | TextInputEdit::Drag { x, y } => Some(Vec2::new(*x as f32, *y as f32)),
+            _ => None,
+        }
+    }
 }
-/// apply a single `TextInputEdit` to a text editor buffer
+
+/// apply a single `TextInputEdit` to a text editor buffer, recording any changes in `history`.
+/// Edits that insert text are reverted if the resulting text doesn't match `filter_mode`.
+/// Returns the change made to the text, or `None` if the text is unchanged.
 pub fn apply_text_input_edit(
+    edit: TextInputEdit,
+    editor: &mut BorrowedWithFontSystem<'_, Editor<'static>>,
+    history: &mut TextInputHistory,
+    max_chars: Option<usize>,
+    filter_mode: &Option<TextInputFilter>,
+) -> Option<Change> {
+    match edit {
+        TextInputEdit::Undo => return history.undo(editor),
+        TextInputEdit::Redo => return history.redo(editor),
+        _ => {}
+    }
+
+    let cursor_before = editor.cursor();
+    let selection_before = editor.selection();
+    let kind = match &edit {
+        _ if selection_before != Selection::None => EditKind::Other,
+        TextInputEdit::Insert(..) => EditKind::Insert,
+        TextInputEdit::Backspace | TextInputEdit::Delete => EditKind::Delete,
+        _ => EditKind::Other,
+    };
+
+    let is_insertion = matches!(
+        edit,
+        TextInputEdit::Insert(..)
+            | TextInputEdit::Paste(_)
+            | TextInputEdit::Enter
+            | TextInputEdit::Indent
+            | TextInputEdit::SetText(_)
+            | TextInputEdit::InsertAt { .. }
+            | TextInputEdit::ReplaceRange { .. }
+            | TextInputEdit::ReplaceRanges(_)
+    );
+
+    editor.start_change();
+    apply_edit(edit, editor, max_chars);
+    let mut change = editor.finish_change().unwrap_or_default();
+
+    if is_insertion
+        && !change.items.is_empty()
+        && let Some(filter) = filter_mode
+        && !filter.is_match(&editor.with_buffer(crate::get_text))
+    {
+        // The resulting text was rejected by the filter, revert the edit
+        change.reverse();
+        editor.apply_change(&change);
+        editor.set_cursor(cursor_before);
+        editor.set_selection(selection_before);
+        editor.set_redraw(true);
+        history.seal();
+        return None;
+    }
+
+    if change.items.is_empty() {
+        history.seal();
+        return None;
+    }
+    history.push(HistoryEntry {
+        change: change.clone(),
+        kind,
+        cursor_before,
+        selection_before,
+        cursor_after: editor.cursor(),
+        selection_after: editor.selection(),
+    });
+    Some(change)
+}
+
+pub(crate) fn apply_edit(
     edit: TextInputEdit,
     editor: &mut BorrowedWithFontSystem<'_, Editor<'static>>,
     max_chars: Option<usize>,
-    _filter_mode: &Option<TextInputFilter>,
 ) {
     match edit {
         TextInputEdit::Motion(motion, with_select) => {
@@ -114,23 +284,37 @@ pub fn apply_text_input_edit(
                 editor.action(Action::Delete);
             }
         }
+        TextInputEdit::DeleteToLineStart => {
+            let line = editor.cursor().line;
+            delete_to(editor, Cursor::new(line, 0), Action::Backspace);
+        }
+        TextInputEdit::DeleteToLineEnd => {
+            let line = editor.cursor().line;
+            let len = editor.with_buffer(|buffer| buffer.lines[line].text().len());
+            delete_to(editor, Cursor::new(line, len), Action::Delete);
+        }
+        TextInputEdit::DeleteWordBackward => {
+            delete_motion(editor, Motion::PreviousWord, Action::Backspace);
+        }
+        TextInputEdit::DeleteWordForward => {
+            delete_motion(editor, Motion::NextWord, Action::Delete);
+        }
         TextInputEdit::Indent => {
             editor.action(Action::Indent);
         }
         TextInputEdit::Unindent => {
             editor.action(Action::Unindent);
         }
-        TextInputEdit::Click { x, y } => {
-            editor.action(Action::Click { x, y });
-        }
-        TextInputEdit::DoubleClick { x, y } => {
-            editor.action(Action::DoubleClick { x, y });
-        }
-        TextInputEdit::TripleClick { x, y } => {
-            editor.action(Action::DoubleClick { x, y });
-        }
-        TextInputEdit::Drag { x, y } => {
-            editor.action(Action::Drag { x, y });
+        TextInputEdit::Click { .. }
+        | TextInputEdit::DoubleClick { .. }
+        | TextInputEdit::TripleClick { .. }
+        | TextInputEdit::Drag { .. } => {
+            let cursor = edit
+                .pointer_position()
+                .and_then(|position| editor.with_buffer(|buffer| hit(buffer, position)));
+            if let Some(cursor) = cursor {
+                apply_pointer_edit(&edit, editor, cursor);
+            }
         }
         TextInputEdit::Scroll { lines } => {
             editor.action(Action::Scroll { lines });
@@ -140,13 +324,23 @@ pub fn apply_text_input_edit(
                 editor.insert_string(&text, None);
             }
         }
-        TextInputEdit::Undo => {
-            // For now, undo functionality is disabled
-            // TODO: Implement proper undo/redo system
+        TextInputEdit::SetText(text) => {
+            replace_range(editor, 0..usize::MAX, &text, max_chars);
+        }
+        TextInputEdit::InsertAt { index, text } => {
+            replace_range(editor, index..index, &text, max_chars);
+        }
+        TextInputEdit::ReplaceRange { range, text } => {
+            replace_range(editor, range, &text, max_chars);
         }
-        TextInputEdit::Redo => {
-            // For now, redo functionality is disabled
-            // TODO: Implement proper undo/redo system
+        TextInputEdit::ReplaceRanges(replacements) => {
+            replace_ranges(editor, replacements, max_chars);
+        }
+        TextInputEdit::Clear => {
+            replace_range(editor, 0..usize::MAX, "", None);
+        }
+        TextInputEdit::Undo | TextInputEdit::Redo => {
+            // handled by `apply_text_input_edit`
         }
         TextInputEdit::SelectAll => {
             editor.action(Action::Motion(Motion::BufferStart));
@@ -157,5 +351,396 @@ pub fn apply_text_input_edit(
         TextInputEdit::Enter => {
             editor.action(Action::Enter);
         }
+        TextInputEdit::AddCaret(cursor) => {
+            // handled by `apply_text_input_edit_at_carets`, otherwise just moves the cursor
+            editor.set_selection(Selection::None);
+            editor.set_cursor(cursor);
+        }
+    }
+}
+
+/// Apply a pointer edit, with `cursor` the cursor under the pointer.
+/// Word and line selections from double and triple clicks are kept while dragging, so they extend by words or lines.
+pub(crate) fn apply_pointer_edit(
+    edit: &TextInputEdit,
+    editor: &mut Editor<'static>,
+    cursor: Cursor,
+) {
+    match edit {
+        TextInputEdit::Click { .. } => {
+            editor.set_selection(Selection::None);
+        }
+        TextInputEdit::DoubleClick { .. } => {
+            editor.set_selection(Selection::Word(cursor));
+        }
+        TextInputEdit::TripleClick { .. } => {
+            editor.set_selection(Selection::Line(cursor));
+        }
+        TextInputEdit::Drag { .. } => {
+            if editor.selection() == Selection::None {
+                let anchor = editor.cursor();
+                editor.set_selection(Selection::Normal(anchor));
+            }
+        }
+        _ => return,
+    }
+    editor.set_cursor(cursor);
+    editor.set_redraw(true);
+}
+
+/// Delete the selection, or the text between the cursor and where `motion` would move it.
+/// If the motion wouldn't move the cursor, applies `fallback` instead.
+fn delete_motion(
+    editor: &mut BorrowedWithFontSystem<'_, Editor<'static>>,
+    motion: Motion,
+    fallback: Action,
+) {
+    if editor.delete_selection() {
+        editor.set_redraw(true);
+        return;
+    }
+    let cursor = editor.cursor();
+    editor.action(Action::Motion(motion));
+    let target = editor.cursor();
+    editor.set_cursor(cursor);
+    delete_to(editor, target, fallback);
+}
+
+/// Delete the selection, or the text between the cursor and `target`.
+/// If `target` is at the cursor, applies `fallback` instead.
+fn delete_to(
+    editor: &mut BorrowedWithFontSystem<'_, Editor<'static>>,
+    target: Cursor,
+    fallback: Action,
+) {
+    if editor.delete_selection() {
+        editor.set_redraw(true);
+        return;
+    }
+    let cursor = editor.cursor();
+    if (target.line, target.index) == (cursor.line, cursor.index) {
+        editor.action(fallback);
+    } else {
+        editor.set_selection(Selection::Normal(target));
+        editor.delete_selection();
+        editor.set_redraw(true);
+    }
+}
+
+/// Replace a byte range of the editor's text, clamped to the length of the text.
+/// Does nothing and logs a warning if the range doesn't lie on char boundaries or the result would exceed `max_chars`.
+fn replace_range(
+    editor: &mut BorrowedWithFontSystem<'_, Editor<'static>>,
+    range: Range<usize>,
+    text: &str,
+    max_chars: Option<usize>,
+) {
+    let current = editor.with_buffer(crate::get_text);
+    let end = range.end.min(current.len());
+    let start = range.start.min(end);
+    if !current.is_char_boundary(start) || !current.is_char_boundary(end) {
+        warn!("text edit rejected, the range {start}..{end} doesn't lie on char boundaries");
+        return;
+    }
+
+    let char_count = |text: &str| text.chars().filter(|ch| *ch != '\n').count();
+    if max_chars.is_some_and(|max_chars| {
+        max_chars < char_count(&current) - char_count(&current[start..end]) + char_count(text)
+    }) {
+        warn!("text edit rejected, the resulting text would exceed `max_chars`");
+        return;
+    }
+
+    let start = editor.with_buffer(|buffer| cursor_at_byte_offset(buffer, start));
+    let end = editor.with_buffer(|buffer| cursor_at_byte_offset(buffer, end));
+    editor.set_selection(Selection::None);
+    editor.delete_range(start, end);
+    let cursor = editor.insert_at(start, text, None);
+    editor.set_cursor(cursor);
+    editor.set_redraw(true);
+}
+
+/// Replace non-overlapping byte ranges of the editor's text.
+/// Does nothing and logs a warning if any of the ranges overlap or don't lie on char boundaries, or the result would exceed `max_chars`.
+fn replace_ranges(
+    editor: &mut BorrowedWithFontSystem<'_, Editor<'static>>,
+    mut replacements: Vec<(Range<usize>, String)>,
+    max_chars: Option<usize>,
+) {
+    if replacements.is_empty() {
+        return;
+    }
+    replacements.sort_by_key(|(range, _)| range.start);
+
+    let current = editor.with_buffer(crate::get_text);
+    let char_count = |text: &str| text.chars().filter(|ch| *ch != '\n').count();
+    let mut removed = 0;
+    let mut added = 0;
+    let mut previous_end = 0;
+    // byte offset of the end of the last inserted text in the new text
+    let mut cursor_offset = 0;
+    for (range, text) in replacements.iter() {
+        if range.start < previous_end
+            || range.end < range.start
+            || current.len() < range.end
+            || !current.is_char_boundary(range.start)
+            || !current.is_char_boundary(range.end)
+        {
+            warn!(
+                "text edit rejected, the range {range:?} overlaps another range or doesn't lie on char boundaries"
+            );
+            return;
+        }
+        cursor_offset = (cursor_offset + range.start - previous_end) + text.len();
+        previous_end = range.end;
+        removed += char_count(&current[range.clone()]);
+        added += char_count(text);
+    }
+
+    if max_chars.is_some_and(|max_chars| max_chars < char_count(&current) - removed + added) {
+        warn!("text edit rejected, the resulting text would exceed `max_chars`");
+        return;
+    }
+
+    editor.set_selection(Selection::None);
+    // replace from the end of the text so the offsets of the earlier ranges stay valid
+    for (range, text) in replacements.iter().rev() {
+        let start = editor.with_buffer(|buffer| cursor_at_byte_offset(buffer, range.start));
+        let end = editor.with_buffer(|buffer| cursor_at_byte_offset(buffer, range.end));
+        editor.delete_range(start, end);
+        editor.insert_at(start, text, None);
+    }
+    let cursor = editor.with_buffer(|buffer| cursor_at_byte_offset(buffer, cursor_offset));
+    editor.set_cursor(cursor);
+    editor.set_redraw(true);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::edit::byte_offset_at_cursor;
+    use crate::tests::test_editor;
+    use crate::tests::test_font_system;
+
+    /// Applies `edit` to `text` with the cursor at the byte offset `cursor`.
+    /// Returns the resulting text and the byte offset of the cursor.
+    fn edited(text: &str, cursor: usize, edit: TextInputEdit) -> (String, usize) {
+        let mut font_system = test_font_system();
+        let mut editor = test_editor(&mut font_system, text, None, cursor);
+        let mut editor = editor.borrow_with(&mut font_system);
+        apply_edit(edit, &mut editor, None);
+        editor.with_buffer(|buffer| {
+            (
+                crate::get_text(buffer),
+                byte_offset_at_cursor(buffer, editor.cursor()),
+            )
+        })
+    }
+
+    /// Checks the results of an edit applied at each cursor position
+    fn assert_edits(text: &str, edit: TextInputEdit, expected: &[(usize, &str, usize)]) {
+        for &(cursor, expected_text, expected_cursor) in expected {
+            assert_eq!(
+                edited(text, cursor, edit.clone()),
+                (expected_text.to_string(), expected_cursor),
+                "{edit:?} in {text:?} at {cursor}"
+            );
+        }
+    }
+
+    #[test]
+    fn delete_words_in_ascii_text() {
+        let text = "hello big world";
+        assert_edits(
+            text,
+            TextInputEdit::DeleteWordBackward,
+            &[
+                (15, "hello big ", 10),
+                (12, "hello big rld", 10),
+                (10, "hello world", 6),
+                (0, text, 0),
+            ],
+        );
+        assert_edits(
+            text,
+            TextInputEdit::DeleteWordForward,
+            &[
+                (0, " big world", 0),
+                (5, "hello world", 5),
+                (8, "hello bi world", 8),
+                (15, text, 15),
+            ],
+        );
+    }
+
+    #[test]
+    fn delete_words_in_cjk_text() {
+        // each ideograph is a word, and is 3 bytes long
+        let text = "你好世界";
+        assert_edits(
+            text,
+            TextInputEdit::DeleteWordBackward,
+            &[(12, "你好世", 9), (6, "你世界", 3)],
+        );
+        assert_edits(
+            text,
+            TextInputEdit::DeleteWordForward,
+            &[(0, "好世界", 0), (9, "你好世", 9)],
+        );
+    }
+
+    #[test]
+    fn delete_words_in_mixed_script_text() {
+        let text = "abc漢字def";
+        assert_edits(
+            text,
+            TextInputEdit::DeleteWordBackward,
+            &[(12, "abc漢字", 9), (9, "abc漢def", 6), (3, "漢字def", 0)],
+        );
+        assert_edits(
+            text,
+            TextInputEdit::DeleteWordForward,
+            &[(0, "漢字def", 0), (3, "abc字def", 3), (9, "abc漢字", 9)],
+        );
+    }
+
+    #[test]
+    fn delete_words_at_line_boundaries() {
+        let text = "ab\ncd";
+        assert_edits(text, TextInputEdit::DeleteWordBackward, &[(3, "abcd", 2)]);
+        assert_edits(text, TextInputEdit::DeleteWordForward, &[(2, "abcd", 2)]);
+    }
+
+    #[test]
+    fn delete_to_line_start_and_end() {
+        let text = "ab\ncd ef\ngh";
+        assert_edits(
+            text,
+            TextInputEdit::DeleteToLineStart,
+            &[
+                (6, "ab\nef\ngh", 3),
+                (8, "ab\n\ngh", 3),
+                (3, "abcd ef\ngh", 2),
+            ],
+        );
+        assert_edits(
+            text,
+            TextInputEdit::DeleteToLineEnd,
+            &[
+                (4, "ab\nc\ngh", 4),
+                (3, "ab\n\ngh", 3),
+                (8, "ab\ncd efgh", 8),
+            ],
+        );
+    }
+
+    #[test]
+    fn deletions_delete_the_selection() {
+        for edit in [
+            TextInputEdit::DeleteWordBackward,
+            TextInputEdit::DeleteWordForward,
+            TextInputEdit::DeleteToLineStart,
+            TextInputEdit::DeleteToLineEnd,
+        ] {
+            let mut font_system = test_font_system();
+            let mut editor = test_editor(&mut font_system, "one two three", Some(2), 9);
+            let mut editor = editor.borrow_with(&mut font_system);
+            apply_edit(edit, &mut editor, None);
+            assert_eq!(editor.with_buffer(crate::get_text), "onhree");
+        }
+    }
+
+    #[test]
+    fn delete_to_line_end_ignores_wrapping() {
+        let mut font_system = test_font_system();
+        let mut editor = test_editor(&mut font_system, "one two three four\nfive", None, 4);
+        let mut editor = editor.borrow_with(&mut font_system);
+        editor.with_buffer_mut(|buffer| buffer.set_size(Some(60.), None));
+        editor.shape_as_needed(true);
+        let wrapped_lines =
+            editor.with_buffer(|buffer| buffer.layout_runs().filter(|run| run.line_i == 0).count());
+        assert!(1 < wrapped_lines);
+
+        apply_edit(TextInputEdit::DeleteToLineEnd, &mut editor, None);
+        assert_eq!(editor.with_buffer(crate::get_text), "one \nfive");
+        apply_edit(TextInputEdit::DeleteToLineStart, &mut editor, None);
+        assert_eq!(editor.with_buffer(crate::get_text), "\nfive");
+    }
+
+    #[test]
+    fn rejected_programmatic_edits_change_nothing() {
+        let edits = [
+            // not on char boundaries, each ideograph is 3 bytes long
+            (
+                TextInputEdit::ReplaceRange {
+                    range: 1..3,
+                    text: "x".to_string(),
+                },
+                None,
+            ),
+            (
+                TextInputEdit::InsertAt {
+                    index: 4,
+                    text: "x".to_string(),
+                },
+                None,
+            ),
+            (
+                TextInputEdit::ReplaceRanges(vec![
+                    (0..3, "x".to_string()),
+                    (4..6, "y".to_string()),
+                ]),
+                None,
+            ),
+            // overlapping
+            (
+                TextInputEdit::ReplaceRanges(vec![
+                    (0..6, "x".to_string()),
+                    (3..9, "y".to_string()),
+                ]),
+                None,
+            ),
+            // exceeding `max_chars`
+            (TextInputEdit::SetText("abcd".to_string()), Some(3)),
+            (
+                TextInputEdit::InsertAt {
+                    index: 9,
+                    text: "a".to_string(),
+                },
+                Some(3),
+            ),
+            (
+                TextInputEdit::ReplaceRanges(vec![
+                    (0..3, "ab".to_string()),
+                    (6..9, "cd".to_string()),
+                ]),
+                Some(3),
+            ),
+        ];
+        for (edit, max_chars) in edits {
+            let mut font_system = test_font_system();
+            let mut editor = test_editor(&mut font_system, "日本語", None, 3);
+            let mut editor = editor.borrow_with(&mut font_system);
+            let mut history = TextInputHistory::default();
+            let change =
+                apply_text_input_edit(edit.clone(), &mut editor, &mut history, max_chars, &None);
+            assert!(change.is_none(), "{edit:?} wasn't rejected");
+            assert_eq!(editor.with_buffer(crate::get_text), "日本語");
+            assert_eq!(editor.cursor(), Cursor::new(0, 3));
+            assert!(!history.can_undo());
+        }
+    }
+
+    #[test]
+    fn programmatic_edits_within_max_chars_are_applied() {
+        let mut font_system = test_font_system();
+        let mut editor = test_editor(&mut font_system, "日本語", None, 0);
+        let mut editor = editor.borrow_with(&mut font_system);
+        let mut history = TextInputHistory::default();
+        let edit =
+            TextInputEdit::ReplaceRanges(vec![(6..9, "ab".to_string()), (0..3, String::new())]);
+        assert!(apply_text_input_edit(edit, &mut editor, &mut history, Some(3), &None).is_some());
+        assert_eq!(editor.with_buffer(crate::get_text), "本ab");
+        assert!(history.can_undo());
     }
 }
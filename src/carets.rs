@@ -0,0 +1,524 @@
+use bevy::math::Vec2;
+use bevy::text::cosmic_text::BorrowedWithFontSystem;
+use bevy::text::cosmic_text::Buffer;
+use bevy::text::cosmic_text::Change;
+use bevy::text::cosmic_text::Cursor;
+use bevy::text::cosmic_text::Edit;
+use bevy::text::cosmic_text::Editor;
+use bevy::text::cosmic_text::Selection;
+
+use crate::TextInputFilter;
+use crate::actions::TextInputEdit;
+use crate::actions::apply_edit;
+use crate::actions::apply_text_input_edit;
+use crate::edit::byte_offset_at_cursor;
+use crate::edit::cursor_at_byte_offset;
+use crate::history::EditKind;
+use crate::history::HistoryEntry;
+use crate::history::TextInputHistory;
+use crate::text_input_pipeline::layout_runs;
+use crate::text_input_pipeline::run_hit;
+
+/// An additional text cursor, see `TextInputBuffer::carets`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Caret {
+    pub cursor: Cursor,
+    /// The other end of the caret's selection, if it has one
+    pub anchor: Option<Cursor>,
+    /// Horizontal position kept by vertical motions
+    pub(crate) x_opt: Option<i32>,
+}
+
+impl Caret {
+    pub fn new(cursor: Cursor) -> Self {
+        Self {
+            cursor,
+            anchor: None,
+            x_opt: None,
+        }
+    }
+
+    /// A caret selecting the text from `anchor` to `cursor`
+    pub fn with_selection(anchor: Cursor, cursor: Cursor) -> Self {
+        Self {
+            cursor,
+            anchor: (anchor != cursor).then_some(anchor),
+            x_opt: None,
+        }
+    }
+
+    /// The start and end of the caret's selection, if it has one
+    pub fn selection_bounds(&self) -> Option<(Cursor, Cursor)> {
+        self.anchor
+            .map(|anchor| (anchor.min(self.cursor), anchor.max(self.cursor)))
+    }
+
+    /// The caret of the editor's own cursor and selection
+    fn of_editor(editor: &Editor<'static>) -> Self {
+        Self {
+            cursor: editor.cursor(),
+            anchor: match editor.selection() {
+                Selection::None => None,
+                Selection::Normal(anchor) | Selection::Line(anchor) | Selection::Word(anchor) => {
+                    Some(anchor)
+                }
+            },
+            x_opt: None,
+        }
+    }
+
+    /// Move the editor's own cursor and selection to the caret
+    fn apply_to_editor(&self, editor: &mut Editor<'static>) {
+        editor.set_selection(match self.anchor {
+            Some(anchor) => Selection::Normal(anchor),
+            None => Selection::None,
+        });
+        editor.set_cursor(self.cursor);
+    }
+
+    /// The start of the caret's selection, or its cursor
+    fn start(&self) -> Cursor {
+        self.anchor
+            .map_or(self.cursor, |anchor| anchor.min(self.cursor))
+    }
+}
+
+/// Length of the buffer's text in bytes, including the newlines between its lines
+fn text_len(buffer: &Buffer) -> usize {
+    buffer
+        .lines
+        .iter()
+        .map(|line| line.text().len() + 1)
+        .sum::<usize>()
+        .saturating_sub(1)
+}
+
+/// Remove carets at the same position as the editor's cursor or an earlier caret
+fn dedup_carets(editor: &Editor<'static>, carets: &mut Vec<Caret>) {
+    let mut seen = vec![editor.cursor()];
+    carets.retain(|caret| {
+        if seen.contains(&caret.cursor) {
+            false
+        } else {
+            seen.push(caret.cursor);
+            true
+        }
+    });
+}
+
+/// Adds a caret at `cursor`. The editor's cursor moves to the new caret and its previous position becomes an additional caret.
+pub(crate) fn add_caret(editor: &mut Editor<'static>, carets: &mut Vec<Caret>, cursor: Cursor) {
+    let previous = Caret::of_editor(editor);
+    if let Some(index) = carets.iter().position(|caret| caret.cursor == cursor) {
+        // adding a caret where one already exists removes it instead
+        carets.remove(index);
+        return;
+    }
+    if previous.cursor == cursor {
+        return;
+    }
+    carets.push(previous);
+    Caret::new(cursor).apply_to_editor(editor);
+}
+
+/// Replaces all the carets. The last caret becomes the editor's cursor.
+pub(crate) fn set_carets(
+    editor: &mut Editor<'static>,
+    carets: &mut Vec<Caret>,
+    mut new_carets: Vec<Caret>,
+) {
+    let Some(primary) = new_carets.pop() else {
+        return;
+    };
+    primary.apply_to_editor(editor);
+    *carets = new_carets;
+    dedup_carets(editor, carets);
+}
+
+/// Carets selecting a rectangle of text, from `anchor` in the coordinates of the buffer's layout to `position`
+/// in the coordinates of `text_input_pipeline::hit`.
+/// Each visible line within the rectangle gets a caret, the caret on the line under `position` is last.
+pub(crate) fn box_selection(buffer: &Buffer, anchor: Vec2, position: Vec2) -> Vec<Caret> {
+    let position = position + scroll_offset(buffer);
+    let (top, bottom) = (anchor.y.min(position.y), anchor.y.max(position.y));
+    let mut carets: Vec<Caret> = layout_runs(buffer)
+        .filter(|run| top < run.line_top + run.line_height && run.line_top <= bottom)
+        .map(|run| Caret::with_selection(run_hit(&run, anchor.x), run_hit(&run, position.x)))
+        .collect();
+    if position.y < anchor.y {
+        // the caret under the pointer is the primary caret
+        carets.reverse();
+    }
+    carets
+}
+
+/// Offset from the coordinates of `text_input_pipeline::hit` to the coordinates of the buffer's layout
+pub(crate) fn scroll_offset(buffer: &Buffer) -> Vec2 {
+    let scroll = buffer.scroll();
+    Vec2::new(scroll.horizontal, scroll.vertical)
+}
+
+/// True for edits that are applied at every caret
+fn is_caret_edit(edit: &TextInputEdit) -> bool {
+    matches!(
+        edit,
+        TextInputEdit::Motion(..)
+            | TextInputEdit::Insert(..)
+            | TextInputEdit::Backspace
+            | TextInputEdit::Delete
+            | TextInputEdit::DeleteToLineStart
+            | TextInputEdit::DeleteToLineEnd
+            | TextInputEdit::DeleteWordBackward
+            | TextInputEdit::DeleteWordForward
+            | TextInputEdit::Enter
+            | TextInputEdit::Indent
+            | TextInputEdit::Unindent
+            | TextInputEdit::Paste(_)
+    )
+}
+
+/// Apply a `TextInputEdit` at the editor's cursor and each of the additional `carets`.
+///
+/// Motions, typing, deletions and pastes are applied at every caret, as a single entry in `history`.
+/// `TextInputEdit::Scroll` keeps the carets, and any other edit removes them before it is applied.
+/// Returns the change made to the text, or `None` if the text is unchanged.
+pub fn apply_text_input_edit_at_carets(
+    edit: TextInputEdit,
+    editor: &mut BorrowedWithFontSystem<'_, Editor<'static>>,
+    carets: &mut Vec<Caret>,
+    history: &mut TextInputHistory,
+    max_chars: Option<usize>,
+    filter_mode: &Option<TextInputFilter>,
+) -> Option<Change> {
+    if let TextInputEdit::AddCaret(cursor) = edit {
+        add_caret(editor, carets, cursor);
+        editor.set_redraw(true);
+        history.seal();
+        return None;
+    }
+
+    if carets.is_empty() || !is_caret_edit(&edit) {
+        if !carets.is_empty() && !matches!(edit, TextInputEdit::Scroll { .. }) {
+            carets.clear();
+            editor.set_redraw(true);
+        }
+        return apply_text_input_edit(edit, editor, history, max_chars, filter_mode);
+    }
+
+    if let TextInputEdit::Motion(motion, with_select) = edit {
+        for caret in carets.iter_mut() {
+            if with_select {
+                caret.anchor.get_or_insert(caret.cursor);
+            } else {
+                caret.anchor = None;
+            }
+            if let Some((cursor, x_opt)) = editor
+                .with_buffer_mut(|buffer| buffer.cursor_motion(caret.cursor, caret.x_opt, motion))
+            {
+                caret.cursor = cursor;
+                caret.x_opt = x_opt;
+            }
+            if caret.anchor == Some(caret.cursor) {
+                caret.anchor = None;
+            }
+        }
+        apply_text_input_edit(edit, editor, history, max_chars, filter_mode);
+        dedup_carets(editor, carets);
+        editor.set_redraw(true);
+        return None;
+    }
+
+    let cursor_before = editor.cursor();
+    let selection_before = editor.selection();
+    let carets_before = carets.clone();
+    let kind = match &edit {
+        _ if selection_before != Selection::None => EditKind::Other,
+        _ if carets.iter().any(|caret| caret.anchor.is_some()) => EditKind::Other,
+        TextInputEdit::Insert(..) => EditKind::Insert,
+        TextInputEdit::Backspace | TextInputEdit::Delete => EditKind::Delete,
+        _ => EditKind::Other,
+    };
+
+    // Edit from the end of the text towards the start, so that the positions of the carets not yet edited stay valid.
+    // Edits only change the text before the carets already edited, so their distance from the end of the text is kept.
+    let mut pending = carets
+        .drain(..)
+        .map(|caret| (caret, false))
+        .chain([(Caret::of_editor(editor), true)])
+        .collect::<Vec<_>>();
+    pending.sort_by_key(|(caret, _)| caret.start());
+
+    editor.start_change();
+    let mut edited = vec![];
+    while let Some((caret, is_primary)) = pending.pop() {
+        caret.apply_to_editor(editor);
+        apply_edit(edit.clone(), editor, max_chars);
+        let caret = Caret::of_editor(editor);
+        let distances = editor.with_buffer(|buffer| {
+            let len = text_len(buffer);
+            let distance = |cursor: Cursor| len - byte_offset_at_cursor(buffer, cursor).min(len);
+            (distance(caret.cursor), caret.anchor.map(distance))
+        });
+        edited.push((distances, is_primary));
+    }
+    let mut change = editor.finish_change().unwrap_or_default();
+
+    let mut primary = None;
+    editor.with_buffer(|buffer| {
+        let len = text_len(buffer);
+        let cursor = |distance: usize| cursor_at_byte_offset(buffer, len.saturating_sub(distance));
+        for ((cursor_distance, anchor_distance), is_primary) in edited {
+            let caret = Caret {
+                cursor: cursor(cursor_distance),
+                anchor: anchor_distance.map(cursor),
+                x_opt: None,
+            };
+            if is_primary {
+                primary = Some(caret);
+            } else {
+                carets.push(caret);
+            }
+        }
+    });
+    if let Some(primary) = primary {
+        primary.apply_to_editor(editor);
+    }
+    // the carets were edited in reverse order
+    carets.reverse();
+    dedup_carets(editor, carets);
+    editor.set_redraw(true);
+
+    let is_insertion = !matches!(
+        edit,
+        TextInputEdit::Backspace
+            | TextInputEdit::Delete
+            | TextInputEdit::DeleteToLineStart
+            | TextInputEdit::DeleteToLineEnd
+            | TextInputEdit::DeleteWordBackward
+            | TextInputEdit::DeleteWordForward
+    );
+    if is_insertion
+        && !change.items.is_empty()
+        && let Some(filter) = filter_mode
+        && !filter.is_match(&editor.with_buffer(crate::get_text))
+    {
+        // The resulting text was rejected by the filter, revert the edit
+        change.reverse();
+        editor.apply_change(&change);
+        editor.set_cursor(cursor_before);
+        editor.set_selection(selection_before);
+        *carets = carets_before;
+        editor.set_redraw(true);
+        history.seal();
+        return None;
+    }
+
+    if change.items.is_empty() {
+        history.seal();
+        return None;
+    }
+    history.push(HistoryEntry {
+        change: change.clone(),
+        kind,
+        cursor_before,
+        selection_before,
+        cursor_after: editor.cursor(),
+        selection_after: editor.selection(),
+    });
+    Some(change)
+}
+
+/// The selected text of the editor and each caret in the order they appear in the text, joined by newlines
+pub(crate) fn copy_selections(
+    editor: &BorrowedWithFontSystem<'_, Editor<'static>>,
+    carets: &[Caret],
+) -> Option<String> {
+    if carets.is_empty() {
+        return editor.copy_selection();
+    }
+    let mut selections = carets
+        .iter()
+        .chain([&Caret::of_editor(editor)])
+        .filter_map(Caret::selection_bounds)
+        .collect::<Vec<_>>();
+    if selections.is_empty() {
+        return None;
+    }
+    selections.sort();
+    let text = editor.with_buffer(|buffer| {
+        let text = crate::get_text(buffer);
+        selections
+            .iter()
+            .map(|(start, end)| {
+                text[byte_offset_at_cursor(buffer, *start)..byte_offset_at_cursor(buffer, *end)]
+                    .to_string()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    });
+    Some(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::test_editor;
+    use crate::tests::test_font_system;
+    use bevy::text::cosmic_text::FontSystem;
+
+    /// Editor containing `text` with its cursor at the byte offset `cursor`, and carets at the byte offsets `carets`
+    fn editor_with_carets(
+        font_system: &mut FontSystem,
+        text: &str,
+        cursor: usize,
+        carets: &[usize],
+    ) -> (Editor<'static>, Vec<Caret>) {
+        let editor = test_editor(font_system, text, None, cursor);
+        let carets = editor.with_buffer(|buffer| {
+            carets
+                .iter()
+                .map(|&offset| Caret::new(cursor_at_byte_offset(buffer, offset)))
+                .collect()
+        });
+        (editor, carets)
+    }
+
+    /// Byte offsets of the editor's cursor and the carets
+    fn offsets(editor: &Editor<'static>, carets: &[Caret]) -> (usize, Vec<usize>) {
+        editor.with_buffer(|buffer| {
+            (
+                byte_offset_at_cursor(buffer, editor.cursor()),
+                carets
+                    .iter()
+                    .map(|caret| byte_offset_at_cursor(buffer, caret.cursor))
+                    .collect(),
+            )
+        })
+    }
+
+    #[test]
+    fn edits_apply_at_every_caret_as_one_history_entry() {
+        let mut font_system = test_font_system();
+        let (mut editor, mut carets) =
+            editor_with_carets(&mut font_system, "ab\ncd\nef", 7, &[1, 4]);
+        let mut editor = editor.borrow_with(&mut font_system);
+        let mut history = TextInputHistory::default();
+
+        let edit = TextInputEdit::Insert('x', false);
+        let change = apply_text_input_edit_at_carets(
+            edit,
+            &mut editor,
+            &mut carets,
+            &mut history,
+            None,
+            &None,
+        );
+        assert_eq!(change.map(|change| change.items.len()), Some(3));
+        assert_eq!(editor.with_buffer(crate::get_text), "axb\ncxd\nexf");
+        // the carets keep their distances from the end of the text
+        assert_eq!(offsets(&editor, &carets), (10, vec![2, 6]));
+
+        // line breaks inserted before a caret move it to the next line
+        let edit = TextInputEdit::Enter;
+        apply_text_input_edit_at_carets(edit, &mut editor, &mut carets, &mut history, None, &None);
+        assert_eq!(editor.with_buffer(crate::get_text), "ax\nb\ncx\nd\nex\nf");
+        assert_eq!(offsets(&editor, &carets), (13, vec![3, 8]));
+        assert_eq!(carets[0].cursor, Cursor::new(1, 0));
+
+        history.undo(&mut editor);
+        history.undo(&mut editor);
+        assert_eq!(editor.with_buffer(crate::get_text), "ab\ncd\nef");
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn rejected_edits_restore_the_carets() {
+        let mut font_system = test_font_system();
+        let (mut editor, mut carets) = editor_with_carets(&mut font_system, "ab\ncd", 5, &[0, 3]);
+        let mut editor = editor.borrow_with(&mut font_system);
+        let mut history = TextInputHistory::default();
+        let carets_before = carets.clone();
+        let filter = Some(TextInputFilter::custom(|text| !text.contains('z')));
+
+        let edit = TextInputEdit::Insert('z', false);
+        let change = apply_text_input_edit_at_carets(
+            edit,
+            &mut editor,
+            &mut carets,
+            &mut history,
+            None,
+            &filter,
+        );
+        assert!(change.is_none());
+        assert_eq!(editor.with_buffer(crate::get_text), "ab\ncd");
+        assert_eq!(carets, carets_before);
+        assert_eq!(offsets(&editor, &carets).0, 5);
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn carets_meeting_after_an_edit_are_merged() {
+        let mut font_system = test_font_system();
+        let (mut editor, mut carets) =
+            editor_with_carets(&mut font_system, "abc def\ngh", 5, &[1, 9]);
+        let mut editor = editor.borrow_with(&mut font_system);
+        let mut history = TextInputHistory::default();
+
+        let edit = TextInputEdit::DeleteToLineEnd;
+        apply_text_input_edit_at_carets(edit, &mut editor, &mut carets, &mut history, None, &None);
+        assert_eq!(editor.with_buffer(crate::get_text), "a\ng");
+        // the carets on the first line both end up at its end
+        assert_eq!(offsets(&editor, &carets), (1, vec![3]));
+    }
+
+    #[test]
+    fn other_edits_remove_the_carets() {
+        let mut font_system = test_font_system();
+        let (mut editor, mut carets) = editor_with_carets(&mut font_system, "ab cd", 5, &[1]);
+        let mut editor = editor.borrow_with(&mut font_system);
+        let mut history = TextInputHistory::default();
+
+        let edit = TextInputEdit::Scroll { lines: 1 };
+        apply_text_input_edit_at_carets(edit, &mut editor, &mut carets, &mut history, None, &None);
+        assert_eq!(carets.len(), 1);
+
+        let edit = TextInputEdit::SelectAll;
+        apply_text_input_edit_at_carets(edit, &mut editor, &mut carets, &mut history, None, &None);
+        assert!(carets.is_empty());
+    }
+
+    #[test]
+    fn box_selection_selects_a_rectangle() {
+        let mut font_system = test_font_system();
+        let mut editor = test_editor(&mut font_system, "abcd\nefgh\nijkl", None, 0);
+        let mut editor = editor.borrow_with(&mut font_system);
+        editor.shape_as_needed(true);
+
+        let (glyph_width, line_height) = editor.with_buffer(|buffer| {
+            let run = layout_runs(buffer).next().unwrap();
+            (run.glyphs[1].x, run.line_height)
+        });
+        let bounds = |carets: &[Caret]| {
+            carets
+                .iter()
+                .map(|caret| {
+                    let (start, end) = caret.selection_bounds().unwrap();
+                    (start.line, start.index, end.index)
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let anchor = Vec2::new(glyph_width + 1., 0.5 * line_height);
+        let position = Vec2::new(3. * glyph_width + 1., 1.5 * line_height);
+        let carets = editor.with_buffer(|buffer| box_selection(buffer, anchor, position));
+        assert_eq!(bounds(&carets), [(0, 1, 3), (1, 1, 3)]);
+        assert_eq!(carets[1].cursor.index, 3);
+
+        // dragging upwards puts the caret under the pointer last
+        let anchor = Vec2::new(3. * glyph_width + 1., 2.5 * line_height);
+        let position = Vec2::new(glyph_width + 1., 0.5 * line_height);
+        let carets = editor.with_buffer(|buffer| box_selection(buffer, anchor, position));
+        assert_eq!(bounds(&carets), [(2, 1, 3), (1, 1, 3), (0, 1, 3)]);
+        assert_eq!(carets[2].cursor.index, 1);
+    }
+}
@@ -1,47 +1,86 @@
+use crate::DisplayText;
+use crate::ImePreedit;
+use crate::TextChangedEvent;
+use crate::TextDelta;
+use crate::TextInputBlurred;
 use crate::TextInputBuffer;
+use crate::TextInputFocused;
 use crate::TextInputGlobalState;
 use crate::TextInputMode;
 use crate::TextInputNode;
 use crate::TextInputQueue;
+use crate::TextInputSelectionChanged;
 use crate::TextInputStyle;
 use crate::TextSubmitEvent;
 use crate::actions::TextInputAction;
 use crate::actions::TextInputEdit;
+use crate::actions::apply_pointer_edit;
 use crate::actions::apply_text_input_edit;
+use crate::carets::add_caret;
+use crate::carets::apply_text_input_edit_at_carets;
+use crate::carets::box_selection;
+use crate::carets::copy_selections;
+use crate::carets::scroll_offset;
+use crate::carets::set_carets;
 use crate::clipboard::Clipboard;
+use crate::gutter::TextInputGutterLayout;
+use crate::history::TextInputHistory;
+use crate::keymap::KeyBinding;
+use crate::keymap::KeyChord;
+use crate::keymap::TextInputKeymap;
+use crate::keymap::binding_action;
+use crate::keymap::find_binding;
+use crate::kill_ring::TextInputKillRing;
+use crate::scrollbar::TextInputScrollbarLayout;
+use crate::search::TextInputSearch;
 use crate::text_input_pipeline::TextInputPipeline;
+use crate::text_input_pipeline::hit;
+use crate::text_input_pipeline::layout_runs;
 use bevy::ecs::change_detection::DetectChanges;
+use bevy::ecs::change_detection::DetectChangesMut;
 use bevy::ecs::component::Component;
+use bevy::ecs::entity::ContainsEntity;
 use bevy::ecs::entity::Entity;
 use bevy::ecs::event::EventReader;
 use bevy::ecs::event::EventWriter;
 use bevy::ecs::observer::Trigger;
 use bevy::ecs::query::With;
+use bevy::ecs::resource::Resource;
 use bevy::ecs::system::Commands;
 use bevy::ecs::system::Local;
 use bevy::ecs::system::NonSend;
 use bevy::ecs::system::Query;
 use bevy::ecs::system::Res;
 use bevy::ecs::system::ResMut;
-use bevy::ecs::system::Single;
+use bevy::ecs::system::SystemParam;
+use bevy::input::ButtonInput;
 use bevy::input::ButtonState;
 use bevy::input::keyboard::Key;
+use bevy::input::keyboard::KeyCode;
 use bevy::input::keyboard::KeyboardInput;
 use bevy::input::mouse::MouseScrollUnit;
 use bevy::input::mouse::MouseWheel;
 use bevy::input_focus::FocusedInput;
 use bevy::input_focus::InputFocus;
+use bevy::input_focus::InputFocusVisible;
+use bevy::input_focus::tab_navigation::NavAction;
+use bevy::input_focus::tab_navigation::TabNavigation;
+use bevy::input_focus::tab_navigation::TabNavigationError;
 use bevy::log::warn;
 use bevy::math::Rect;
-use bevy::picking::events::Click;
+use bevy::math::Vec2;
 use bevy::picking::events::Drag;
-use bevy::picking::events::Move;
+use bevy::picking::events::DragEnd;
 use bevy::picking::events::Pointer;
 use bevy::picking::events::Pressed;
 use bevy::picking::hover::HoverMap;
 use bevy::picking::pointer::PointerButton;
+use bevy::render::camera::Camera;
+use bevy::render::camera::NormalizedRenderTarget;
 use bevy::text::cosmic_text::Action;
 use bevy::text::cosmic_text::BorrowedWithFontSystem;
+use bevy::text::cosmic_text::Buffer;
+use bevy::text::cosmic_text::Cursor;
 use bevy::text::cosmic_text::Edit;
 use bevy::text::cosmic_text::Editor;
 use bevy::text::cosmic_text::Motion;
@@ -49,7 +88,9 @@ use bevy::text::cosmic_text::Selection;
 use bevy::time::Time;
 use bevy::transform::components::GlobalTransform;
 use bevy::ui::ComputedNode;
+use bevy::ui::ComputedNodeTarget;
 use bevy::window::Ime;
+use bevy::window::PrimaryWindow;
 use bevy::window::Window;
 use bevy::winit::WinitWindows;
 
@@ -88,73 +129,162 @@ pub fn cursor_at_line_end(editor: &mut BorrowedWithFontSystem<Editor<'_>>) -> bo
     })
 }
 
+/// Converts a byte offset into the text returned by `TextInputBuffer::get_text` to a cursor.
+/// Offsets past the end of the text are clamped to the end of the buffer.
+pub fn cursor_at_byte_offset(buffer: &bevy::text::cosmic_text::Buffer, offset: usize) -> Cursor {
+    let mut line_start = 0;
+    for (line_index, line) in buffer.lines.iter().enumerate() {
+        let line_end = line_start + line.text().len();
+        if offset <= line_end {
+            return Cursor::new(line_index, offset - line_start);
+        }
+        // skip the newline separating the lines
+        line_start = line_end + 1;
+    }
+    buffer
+        .lines
+        .last()
+        .map(|line| Cursor::new(buffer.lines.len() - 1, line.text().len()))
+        .unwrap_or_default()
+}
+
+/// Converts a cursor to a byte offset into the text returned by `TextInputBuffer::get_text`.
+pub fn byte_offset_at_cursor(buffer: &bevy::text::cosmic_text::Buffer, cursor: Cursor) -> usize {
+    buffer
+        .lines
+        .iter()
+        .take(cursor.line)
+        .map(|line| line.text().len() + 1)
+        .sum::<usize>()
+        + cursor.index
+}
+
 pub(crate) fn is_buffer_empty(buffer: &bevy::text::cosmic_text::Buffer) -> bool {
-    buffer.lines.len() == 0 || (buffer.lines.len() == 1 && buffer.lines[0].text().is_empty())
+    buffer.lines.is_empty() || (buffer.lines.len() == 1 && buffer.lines[0].text().is_empty())
+}
+
+/// Returns the cursor in the editor's text under a position relative to the top left of the text area of a text input.
+/// Inputs showing a `DisplayText`, such as password inputs, are hit tested against the displayed text.
+fn display_hit(
+    editor: &Editor<'static>,
+    display_text: Option<&DisplayText>,
+    position: Vec2,
+) -> Option<Cursor> {
+    match display_text {
+        Some(display_text) => {
+            let cursor = hit(&display_text.buffer, position)?;
+            Some(editor.with_buffer(|buffer| display_text.buffer_cursor(buffer, cursor)))
+        }
+        None => editor.with_buffer(|buffer| hit(buffer, position)),
+    }
 }
 
+#[allow(clippy::type_complexity)]
 pub(crate) fn on_drag_text_input(
     trigger: Trigger<Pointer<Drag>>,
     mut node_query: Query<(
         &ComputedNode,
         &GlobalTransform,
         &mut TextInputBuffer,
+        &mut TextInputQueue,
         &TextInputNode,
+        Option<&TextInputScrollbarLayout>,
+        Option<&TextInputGutterLayout>,
     )>,
-    mut text_input_pipeline: ResMut<TextInputPipeline>,
     input_focus: Res<InputFocus>,
 ) {
     if trigger.button != PointerButton::Primary {
         return;
     }
 
-    if !input_focus
+    if input_focus
         .0
-        .is_some_and(|input_focus_entity| input_focus_entity == trigger.target)
+        .is_none_or(|input_focus_entity| input_focus_entity != trigger.target)
     {
         return;
     }
 
-    let Ok((node, transform, mut buffer, input)) = node_query.get_mut(trigger.target) else {
+    let Ok((node, transform, mut buffer, mut queue, input, scrollbars, gutter)) =
+        node_query.get_mut(trigger.target)
+    else {
         return;
     };
 
-    if !input.is_enabled || !input.focus_on_pointer_down {
+    if !input.is_enabled
+        || !input.focus_on_pointer_down
+        || scrollbars.is_some_and(|scrollbars| scrollbars.captured)
+    {
         return;
     }
 
     let rect = Rect::from_center_size(transform.translation().truncate(), node.size());
 
-    let position =
-        trigger.pointer_location.position * node.inverse_scale_factor().recip() - rect.min;
+    let position = trigger.pointer_location.position * node.inverse_scale_factor().recip()
+        - rect.min
+        - gutter.map_or(0., |gutter| gutter.width) * Vec2::X;
 
-    let mut editor = buffer
-        .editor
-        .borrow_with(&mut text_input_pipeline.font_system);
+    buffer.drag_position = Some(position);
 
-    let scroll = editor.with_buffer(|buffer| buffer.scroll());
+    if let Some(anchor) = buffer.box_anchor {
+        let TextInputBuffer { editor, carets, .. } = &mut *buffer;
+        let box_carets = editor.with_buffer(|buffer| box_selection(buffer, anchor, position));
+        set_carets(editor, carets, box_carets);
+        editor.set_redraw(true);
+        return;
+    }
 
-    editor.action(Action::Drag {
-        x: position.x as i32 + scroll.horizontal as i32,
+    queue.add(TextInputAction::Edit(TextInputEdit::Drag {
+        x: position.x as i32,
         y: position.y as i32,
-    });
+    }));
+}
+
+pub(crate) fn on_drag_end_text_input(
+    trigger: Trigger<Pointer<DragEnd>>,
+    mut node_query: Query<&mut TextInputBuffer>,
+) {
+    if trigger.button != PointerButton::Primary {
+        return;
+    }
+
+    if let Ok(mut buffer) = node_query.get_mut(trigger.target) {
+        buffer.drag_position = None;
+    }
 }
 
+#[allow(clippy::type_complexity)]
 pub(crate) fn on_text_input_pressed(
     trigger: Trigger<Pointer<Pressed>>,
     mut node_query: Query<(
         &ComputedNode,
         &GlobalTransform,
         &mut TextInputBuffer,
+        &mut TextInputQueue,
+        &mut MultiClickData,
         &TextInputNode,
+        Option<&TextInputScrollbarLayout>,
+        Option<&TextInputGutterLayout>,
     )>,
-    mut text_input_pipeline: ResMut<TextInputPipeline>,
     mut input_focus: ResMut<InputFocus>,
+    keys: Res<ButtonInput<KeyCode>>,
+    multi_click_settings: Res<MultiClickSettings>,
+    time: Res<Time>,
 ) {
     if trigger.button != PointerButton::Primary {
         return;
     }
 
-    let Ok((node, transform, mut buffer, input)) = node_query.get_mut(trigger.target) else {
+    let Ok((
+        node,
+        transform,
+        mut buffer,
+        mut queue,
+        mut multi_click_data,
+        input,
+        scrollbars,
+        gutter,
+    )) = node_query.get_mut(trigger.target)
+    else {
         return;
     };
 
@@ -162,28 +292,86 @@ pub(crate) fn on_text_input_pressed(
         return;
     }
 
-    if !input_focus
+    let rect = Rect::from_center_size(transform.translation().truncate(), node.size());
+
+    let position =
+        trigger.pointer_location.position * node.inverse_scale_factor().recip() - rect.min;
+
+    // Presses on scrollbars are handled by the scrollbar's observers
+    if scrollbars.is_some_and(|scrollbars| scrollbars.at(position).is_some()) {
+        return;
+    }
+
+    if input_focus
         .get()
-        .is_some_and(|active_input| active_input == trigger.target)
+        .is_none_or(|active_input| active_input != trigger.target)
     {
         input_focus.set(trigger.target);
     }
 
-    let rect = Rect::from_center_size(transform.translation().truncate(), node.size());
+    buffer.box_anchor = None;
+    buffer.drag_position = None;
 
-    let position =
-        trigger.pointer_location.position * node.inverse_scale_factor().recip() - rect.min;
+    if let Some(gutter) = gutter
+        && position.x < gutter.width
+    {
+        // select the line of the number under the pointer
+        let y = position.y + buffer.display_scroll().vertical;
+        if let Some(number) = gutter.number_at(y) {
+            buffer.carets.clear();
+            buffer.editor.set_selection(Selection::Normal(number.start));
+            buffer.editor.set_cursor(number.end);
+            buffer.editor.set_redraw(true);
+        }
+        *multi_click_data = MultiClickData::default();
+        return;
+    }
 
-    let mut editor = buffer
-        .editor
-        .borrow_with(&mut text_input_pipeline.font_system);
+    let position = position - gutter.map_or(0., |gutter| gutter.width) * Vec2::X;
 
-    let scroll = editor.with_buffer(|buffer| buffer.scroll());
+    // Read the keys at the time of the click, the modifiers in `TextInputGlobalState` are only updated for focused inputs
+    if keys.any_pressed([KeyCode::AltLeft, KeyCode::AltRight]) && input.mode.mask().is_none() {
+        // Alt+click adds a caret, Alt+drag selects a rectangle of text
+        let TextInputBuffer {
+            editor,
+            carets,
+            box_anchor,
+            ..
+        } = &mut *buffer;
+        if let Some(cursor) = editor.with_buffer(|buffer| hit(buffer, position)) {
+            add_caret(editor, carets, cursor);
+            editor.set_redraw(true);
+        }
+        *box_anchor = Some(editor.with_buffer(|buffer| position + scroll_offset(buffer)));
+        *multi_click_data = MultiClickData::default();
+        return;
+    }
 
-    editor.action(Action::Click {
-        x: position.x as i32 + scroll.horizontal as i32,
-        y: position.y as i32,
-    });
+    let now = time.elapsed_secs();
+    let click_count = if multi_click_data.click_count > 0
+        && now - multi_click_data.last_click_time <= multi_click_settings.period
+        && multi_click_data.position.distance(position)
+            <= multi_click_settings.max_distance * node.inverse_scale_factor().recip()
+    {
+        multi_click_data.click_count % 3 + 1
+    } else {
+        1
+    };
+    *multi_click_data = MultiClickData {
+        last_click_time: now,
+        click_count,
+        position,
+    };
+
+    let (x, y) = (position.x as i32, position.y as i32);
+    let edit = match click_count {
+        // password inputs are treated as a single word
+        2 if input.mode.mask().is_some() => TextInputEdit::SelectAll,
+        2 => TextInputEdit::DoubleClick { x, y },
+        3 => TextInputEdit::TripleClick { x, y },
+        _ => TextInputEdit::Click { x, y },
+    };
+    queue.add(TextInputAction::Edit(edit));
 }
 
 /// Updates the scroll position of scrollable nodes in response to mouse input
@@ -222,298 +410,203 @@ pub fn mouse_wheel_scroll(
     }
 }
 
-const MULTI_CLICK_PERIOD: f32 = 0.5; // seconds
+/// Speed of the scrolling while drag selecting with the pointer outside of a text input
+#[derive(Resource, Copy, Clone, Debug, PartialEq)]
+pub struct DragScrollSettings {
+    /// Pixels scrolled per second for each pixel the pointer is outside of the text area, in logical pixels
+    pub speed: f32,
+    /// Maximum pixels scrolled per second, in logical pixels
+    pub max_speed: f32,
+}
 
-#[derive(Component)]
-pub struct MultiClickData {
-    last_click_time: f32,
-    click_count: usize,
+impl Default for DragScrollSettings {
+    fn default() -> Self {
+        Self {
+            speed: 10.,
+            max_speed: 2000.,
+        }
+    }
 }
 
-pub fn on_multi_click_set_selection(
-    click: Trigger<Pointer<Click>>,
-    time: Res<Time>,
-    mut text_input_nodes: Query<(
+/// How far `position` is before the start or after the end of `0..=length`
+fn overflow(position: f32, length: f32) -> f32 {
+    if position < 0. {
+        position
+    } else {
+        (position - length).max(0.)
+    }
+}
+
+/// Scrolls text inputs while the pointer is dragged outside of them, extending the selection towards the pointer.
+/// Multi-line inputs scroll vertically and single line inputs horizontally, faster the further away the pointer is.
+pub fn drag_auto_scroll(
+    mut query: Query<(
+        &ComputedNode,
         &TextInputNode,
-        &mut TextInputQueue,
         &mut TextInputBuffer,
-        &GlobalTransform,
-        &ComputedNode,
+        &mut TextInputQueue,
     )>,
-    mut multi_click_datas: Query<&mut MultiClickData>,
     mut text_input_pipeline: ResMut<TextInputPipeline>,
-    mut commands: Commands,
+    settings: Res<DragScrollSettings>,
+    time: Res<Time>,
 ) {
-    if click.button != PointerButton::Primary {
-        return;
-    }
-
-    let entity = click.target();
+    let font_system = &mut text_input_pipeline.font_system;
+    for (node, input, mut buffer, mut queue) in query.iter_mut() {
+        let Some(position) = buffer.drag_position else {
+            continue;
+        };
+        if !input.is_enabled {
+            continue;
+        }
 
-    let Ok((input, mut queue, mut buffer, transform, node)) = text_input_nodes.get_mut(entity)
-    else {
-        return;
-    };
+        let scale_factor = node.inverse_scale_factor().recip();
+        let (width, height) = buffer.editor.with_buffer(|buffer| buffer.size());
+        let (width, height) = (width.unwrap_or(f32::MAX), height.unwrap_or(f32::MAX));
+        let is_multiline = matches!(input.mode, TextInputMode::MultiLine { .. });
+        let distance = if is_multiline {
+            overflow(position.y, height)
+        } else {
+            overflow(position.x, width)
+        };
+        if distance == 0. {
+            continue;
+        }
 
-    if !input.is_enabled || !input.focus_on_pointer_down {
-        return;
-    }
+        let speed = (distance.abs() / scale_factor * settings.speed).min(settings.max_speed);
+        let delta = distance.signum() * speed * scale_factor * time.delta_secs();
 
-    let now = time.elapsed_secs();
-    if let Ok(mut multi_click_data) = multi_click_datas.get_mut(entity) {
-        if now - multi_click_data.last_click_time
-            <= MULTI_CLICK_PERIOD * multi_click_data.click_count as f32
-        {
-            let rect = Rect::from_center_size(transform.translation().truncate(), node.size());
-
-            let position =
-                click.pointer_location.position * node.inverse_scale_factor().recip() - rect.min;
-            let mut editor = buffer
-                .editor
-                .borrow_with(&mut text_input_pipeline.font_system);
-            let scroll = editor.with_buffer(|buffer| buffer.scroll());
-            match multi_click_data.click_count {
-                1 => {
-                    multi_click_data.click_count += 1;
-                    multi_click_data.last_click_time = now;
-
-                    queue.add(TextInputAction::Edit(TextInputEdit::DoubleClick {
-                        x: position.x as i32 + scroll.horizontal as i32,
-                        y: position.y as i32,
-                    }));
-                    return;
-                }
-                2 => {
-                    editor.action(Action::Motion(Motion::ParagraphStart));
-                    let cursor = editor.cursor();
-                    editor.set_selection(Selection::Normal(cursor));
-                    editor.action(Action::Motion(Motion::ParagraphEnd));
-                    if let Ok(mut entity) = commands.get_entity(entity) {
-                        entity.try_remove::<MultiClickData>();
-                    }
-                    return;
-                }
-                _ => (),
+        let TextInputBuffer {
+            editor,
+            display_text,
+            carets,
+            box_anchor,
+            ..
+        } = &mut *buffer;
+        if is_multiline {
+            editor.borrow_with(font_system).with_buffer_mut(|buffer| {
+                let mut scroll = buffer.scroll();
+                scroll.vertical += delta;
+                buffer.set_scroll(scroll);
+                buffer.shape_until_scroll(false);
+            });
+        } else {
+            let scroll_horizontal = |buffer: &mut Buffer| {
+                let line_width = layout_runs(buffer).fold(0., |w: f32, run| w.max(run.line_w));
+                let mut scroll = buffer.scroll();
+                scroll.horizontal =
+                    (scroll.horizontal + delta).clamp(0., (line_width - width).max(0.));
+                buffer.set_scroll(scroll);
+            };
+            match display_text.as_mut() {
+                Some(display_text) => scroll_horizontal(&mut display_text.buffer),
+                None => editor.with_buffer_mut(scroll_horizontal),
             }
         }
-    }
-    if let Ok(mut entity) = commands.get_entity(entity) {
-        entity.try_insert(MultiClickData {
-            last_click_time: now,
-            click_count: 1,
-        });
+        editor.set_redraw(true);
+
+        // extend the selection to the edge of the text area nearest the pointer
+        let position = position.clamp(Vec2::ZERO, Vec2::new(width, height));
+        if let Some(anchor) = *box_anchor {
+            let box_carets = editor.with_buffer(|buffer| box_selection(buffer, anchor, position));
+            set_carets(editor, carets, box_carets);
+        } else {
+            queue.add(TextInputAction::Edit(TextInputEdit::Drag {
+                x: position.x as i32,
+                y: position.y as i32,
+            }));
+        }
     }
 }
 
-pub fn on_move_clear_multi_click(move_: Trigger<Pointer<Move>>, mut commands: Commands) {
-    if let Ok(mut entity) = commands.get_entity(move_.target()) {
-        entity.try_remove::<MultiClickData>();
+/// Timing of double and triple clicks in text inputs
+#[derive(Resource, Copy, Clone, Debug, PartialEq)]
+pub struct MultiClickSettings {
+    /// Maximum time between the presses of a multi-click in seconds
+    pub period: f32,
+    /// Maximum distance the pointer can move between the presses of a multi-click in logical pixels
+    pub max_distance: f32,
+}
+
+impl Default for MultiClickSettings {
+    fn default() -> Self {
+        Self {
+            period: 0.5,
+            max_distance: 4.,
+        }
     }
 }
 
+/// The presses counted towards a multi-click on a text input
+#[derive(Component, Default, Debug)]
+pub struct MultiClickData {
+    last_click_time: f32,
+    /// 1 for a single click, 2 for a double click and 3 for a triple click
+    click_count: usize,
+    /// Position of the last press relative to the text area, in physical pixels
+    position: Vec2,
+}
+
+/// Queues the action bound to a key in `keymaps`, in priority order, or inserts the typed characters of unbound keys.
+/// Updates the modifier keys and overwrite mode in `global_state`.
 pub fn queue_text_input_action(
+    keymaps: &[&TextInputKeymap],
     input_mode: &TextInputMode,
-    shift_pressed: &mut bool,
-    overwrite_mode: &mut bool,
-    command_pressed: &mut bool,
+    global_state: &mut TextInputGlobalState,
     keyboard_input: &KeyboardInput,
-    mut queue: impl FnMut(TextInputAction) -> (),
+    mut queue: impl FnMut(TextInputAction),
 ) {
+    let modifiers = &mut global_state.modifiers;
+    let is_pressed = keyboard_input.state == ButtonState::Pressed;
     match keyboard_input.logical_key {
         Key::Shift => {
-            *shift_pressed = keyboard_input.state == ButtonState::Pressed;
+            modifiers.shift = is_pressed;
             return;
         }
         Key::Control => {
-            *command_pressed = keyboard_input.state == ButtonState::Pressed;
+            modifiers.ctrl = is_pressed;
+            return;
+        }
+        Key::Alt => {
+            modifiers.alt = is_pressed;
             return;
         }
-        #[cfg(target_os = "macos")]
-        Key::Super => {
-            *command_pressed = keyboard_input.state == ButtonState::Pressed;
+        Key::Super | Key::Meta => {
+            modifiers.super_key = is_pressed;
             return;
         }
         _ => {}
     };
 
-    if keyboard_input.state.is_pressed() {
-        if *command_pressed {
-            match &keyboard_input.logical_key {
-                Key::Character(str) => {
-                    if let Some(char) = str.chars().next() {
-                        // convert to lowercase so that the commands work with capslock on
-                        match (char.to_ascii_lowercase(), *shift_pressed) {
-                            ('c', false) => {
-                                // copy
-                                queue(TextInputAction::Copy);
-                            }
-                            ('x', false) => {
-                                // cut
-                                queue(TextInputAction::Cut);
-                            }
-                            ('v', false) => {
-                                // paste
-                                queue(TextInputAction::Paste);
-                            }
-                            ('z', false) => {
-                                queue(TextInputAction::Edit(TextInputEdit::Undo));
-                            }
-                            #[cfg(target_os = "macos")]
-                            ('z', true) => {
-                                queue(TextInputAction::Edit(TextInputEdit::Redo));
-                            }
-                            ('y', false) => {
-                                queue(TextInputAction::Edit(TextInputEdit::Redo));
-                            }
-                            ('a', false) => {
-                                // select all
-                                queue(TextInputAction::Edit(TextInputEdit::SelectAll));
-                            }
-                            _ => {
-                                // not recognised, ignore
-                            }
-                        }
-                    }
-                }
-                Key::ArrowLeft => {
-                    queue(TextInputAction::Edit(TextInputEdit::Motion(
-                        Motion::PreviousWord,
-                        *shift_pressed,
-                    )));
-                }
-                Key::ArrowRight => {
-                    queue(TextInputAction::Edit(TextInputEdit::Motion(
-                        Motion::NextWord,
-                        *shift_pressed,
-                    )));
-                }
-                Key::ArrowUp => {
-                    if matches!(input_mode, TextInputMode::MultiLine { .. }) {
-                        queue(TextInputAction::Edit(TextInputEdit::Scroll { lines: -1 }));
-                    }
-                }
-                Key::ArrowDown => {
-                    if matches!(input_mode, TextInputMode::MultiLine { .. }) {
-                        queue(TextInputAction::Edit(TextInputEdit::Scroll { lines: 1 }));
-                    }
-                }
-                Key::Home => {
-                    queue(TextInputAction::Edit(TextInputEdit::Motion(
-                        Motion::BufferStart,
-                        *shift_pressed,
-                    )));
-                }
-                Key::End => {
-                    queue(TextInputAction::Edit(TextInputEdit::Motion(
-                        Motion::BufferEnd,
-                        *shift_pressed,
-                    )));
-                }
-                _ => {
-                    // not recognised, ignore
-                }
+    if !is_pressed {
+        return;
+    }
+
+    let chord = KeyChord::from_key(&keyboard_input.logical_key, *modifiers);
+    match find_binding(keymaps, &chord) {
+        Some((KeyBinding::ToggleOverwrite, _)) => {
+            global_state.overwrite_mode = !global_state.overwrite_mode;
+        }
+        Some((binding, with_select)) => {
+            if let Some(action) = binding_action(binding, with_select, input_mode) {
+                queue(action);
             }
-        } else {
-            match &keyboard_input.logical_key {
-                Key::Character(_) | Key::Space => {
-                    let str = if let Key::Character(str) = &keyboard_input.logical_key {
-                        str.chars()
-                    } else {
-                        " ".chars()
-                    };
-                    for char in str {
-                        queue(TextInputAction::Edit(TextInputEdit::Insert(
-                            char,
-                            *overwrite_mode,
-                        )));
-                    }
-                }
-                Key::Enter => match (*shift_pressed, input_mode) {
-                    (false, TextInputMode::MultiLine { .. }) => {
-                        queue(TextInputAction::Edit(TextInputEdit::Enter));
-                    }
-                    _ => {
-                        queue(TextInputAction::Submit);
-                    }
-                },
-                Key::Backspace => {
-                    queue(TextInputAction::Edit(TextInputEdit::Backspace));
-                }
-                Key::Delete => {
-                    if *shift_pressed {
-                        queue(TextInputAction::Cut);
-                    } else {
-                        queue(TextInputAction::Edit(TextInputEdit::Delete));
-                    }
-                }
-                Key::PageUp => {
-                    queue(TextInputAction::Edit(TextInputEdit::Motion(
-                        Motion::PageUp,
-                        *shift_pressed,
-                    )));
-                }
-                Key::PageDown => {
-                    queue(TextInputAction::Edit(TextInputEdit::Motion(
-                        Motion::PageDown,
-                        *shift_pressed,
-                    )));
-                }
-                Key::ArrowLeft => {
-                    queue(TextInputAction::Edit(TextInputEdit::Motion(
-                        Motion::Left,
-                        *shift_pressed,
-                    )));
-                }
-                Key::ArrowRight => {
-                    queue(TextInputAction::Edit(TextInputEdit::Motion(
-                        Motion::Right,
-                        *shift_pressed,
-                    )));
-                }
-                Key::ArrowUp => {
-                    queue(TextInputAction::Edit(TextInputEdit::Motion(
-                        Motion::Up,
-                        *shift_pressed,
-                    )));
-                }
-                Key::ArrowDown => {
-                    queue(TextInputAction::Edit(TextInputEdit::Motion(
-                        Motion::Down,
-                        *shift_pressed,
-                    )));
-                }
-                Key::Home => {
-                    queue(TextInputAction::Edit(TextInputEdit::Motion(
-                        Motion::Home,
-                        *shift_pressed,
-                    )));
-                }
-                Key::End => {
-                    queue(TextInputAction::Edit(TextInputEdit::Motion(
-                        Motion::End,
-                        *shift_pressed,
-                    )));
-                }
-                Key::Escape => {
-                    queue(TextInputAction::Edit(TextInputEdit::Escape));
-                }
-                Key::Tab => {
-                    if matches!(input_mode, TextInputMode::MultiLine { .. }) {
-                        if *shift_pressed {
-                            queue(TextInputAction::Edit(TextInputEdit::Unindent));
-                        } else {
-                            queue(TextInputAction::Edit(TextInputEdit::Indent));
-                        }
-                    }
-                }
-                Key::Insert => {
-                    if !*shift_pressed {
-                        *overwrite_mode = !*overwrite_mode;
-                    }
-                }
-                _ => {}
+        }
+        None if !modifiers.is_command() => {
+            let text = match &keyboard_input.logical_key {
+                Key::Character(str) => str.as_str(),
+                Key::Space => " ",
+                _ => return,
+            };
+            for char in text.chars() {
+                queue(TextInputAction::Edit(TextInputEdit::Insert(
+                    char,
+                    global_state.overwrite_mode,
+                )));
             }
         }
+        None => {
+            // not recognised, ignore
+        }
     }
 }
 
@@ -531,25 +624,138 @@ pub fn cursor_blink_system(
     }
 }
 
+/// Sends `TextInputSelectionChanged` for text inputs whose cursor, selection or carets changed since the last update
+pub fn send_selection_changed_events(
+    mut query: Query<(Entity, &mut TextInputBuffer)>,
+    mut commands: Commands,
+    mut selection_writer: EventWriter<TextInputSelectionChanged>,
+) {
+    for (entity, mut buffer) in query.iter_mut() {
+        // the horizontal positions kept by the carets aren't part of their selections
+        let carets = buffer
+            .carets
+            .iter()
+            .map(|caret| (caret.cursor, caret.anchor))
+            .collect();
+        let selection = (buffer.editor.cursor(), buffer.editor.selection(), carets);
+        if selection == buffer.last_selection {
+            continue;
+        }
+        // recording the selection isn't a change to the buffer
+        buffer.bypass_change_detection().last_selection = selection;
+
+        let bytes = selected_range(&buffer.editor);
+        let chars = buffer.editor.with_buffer(|buffer| {
+            let text = crate::get_text(buffer);
+            let start = text[..bytes.start].chars().count();
+            start..start + text[bytes.clone()].chars().count()
+        });
+        let carets = buffer.editor.with_buffer(|editor_buffer| {
+            buffer
+                .carets
+                .iter()
+                .map(|caret| {
+                    let (start, end) = caret
+                        .selection_bounds()
+                        .unwrap_or((caret.cursor, caret.cursor));
+                    byte_offset_at_cursor(editor_buffer, start)
+                        ..byte_offset_at_cursor(editor_buffer, end)
+                })
+                .collect()
+        });
+        let event = TextInputSelectionChanged {
+            entity,
+            bytes,
+            chars,
+            carets,
+        };
+        commands.trigger_targets(event.clone(), entity);
+        selection_writer.write(event);
+    }
+}
+
+/// True for actions that can change the text of an input
+fn may_change_text(action: &TextInputAction) -> bool {
+    match action {
+        TextInputAction::Edit(edit) => !matches!(
+            edit,
+            TextInputEdit::Motion(..)
+                | TextInputEdit::Escape
+                | TextInputEdit::Click { .. }
+                | TextInputEdit::DoubleClick { .. }
+                | TextInputEdit::TripleClick { .. }
+                | TextInputEdit::Drag { .. }
+                | TextInputEdit::Scroll { .. }
+                | TextInputEdit::SelectAll
+                | TextInputEdit::AddCaret(_)
+        ),
+        TextInputAction::Cut
+        | TextInputAction::PasteDeferred(_)
+        | TextInputAction::Replace(_)
+        | TextInputAction::ReplaceAll(_)
+        | TextInputAction::Kill(_)
+        | TextInputAction::Yank
+        | TextInputAction::YankPop => true,
+        _ => false,
+    }
+}
+
+/// True for actions from the user that can change the text of an input, these are rejected by read-only inputs
+fn is_user_edit(action: &TextInputAction) -> bool {
+    match action {
+        TextInputAction::Edit(
+            TextInputEdit::SetText(_)
+            | TextInputEdit::InsertAt { .. }
+            | TextInputEdit::ReplaceRange { .. }
+            | TextInputEdit::ReplaceRanges(_)
+            | TextInputEdit::Clear,
+        ) => false,
+        TextInputAction::Paste => true,
+        action => may_change_text(action),
+    }
+}
+
 pub fn process_text_input_queues(
     mut query: Query<(
         Entity,
         &TextInputNode,
         &mut TextInputBuffer,
         &mut TextInputQueue,
+        &mut TextInputHistory,
     )>,
     mut text_input_pipeline: ResMut<TextInputPipeline>,
     mut submit_writer: EventWriter<TextSubmitEvent>,
+    mut changed_writer: EventWriter<TextChangedEvent>,
+    mut commands: Commands,
     mut clipboard: ResMut<Clipboard>,
+    mut kill_ring: ResMut<TextInputKillRing>,
 ) {
-    let mut font_system = &mut text_input_pipeline.font_system;
+    let font_system = &mut text_input_pipeline.font_system;
 
-    for (entity, node, mut buffer, mut actions_queue) in query.iter_mut() {
+    for (entity, node, mut buffer, mut actions_queue, mut history) in query.iter_mut() {
         let TextInputBuffer {
-            editor, ..
+            editor,
+            span_styles,
+            needs_update,
+            search,
+            carets,
+            display_text,
+            text_generation,
+            ..
         } = &mut *buffer;
-        let mut editor = editor.borrow_with(&mut font_system);
+        let mut editor = editor.borrow_with(font_system);
         while let Some(action) = actions_queue.next() {
+            if !matches!(
+                action,
+                TextInputAction::Kill(_) | TextInputAction::Yank | TextInputAction::YankPop
+            ) {
+                kill_ring.interrupt(entity);
+            }
+            if node.is_read_only && is_user_edit(&action) {
+                continue;
+            }
+            // The change made to the text by the action
+            let mut change = None;
             match action {
                 TextInputAction::Submit => {
                     let text = editor.with_buffer(crate::get_text);
@@ -559,19 +765,24 @@ pub fn process_text_input_queues(
                         actions_queue.add_front(TextInputAction::Edit(TextInputEdit::SelectAll));
                     }
                 }
+                TextInputAction::Cut | TextInputAction::Copy if node.mode.mask().is_some() => {
+                    // The contents of password inputs can't be copied
+                }
                 TextInputAction::Cut => {
-                    if let Some(text) = editor.copy_selection() {
+                    if let Some(text) = copy_selections(&editor, carets) {
                         let _ = clipboard.set_text(text);
-                        apply_text_input_edit(
+                        change = apply_text_input_edit_at_carets(
                             TextInputEdit::Delete,
                             &mut editor,
+                            carets,
+                            &mut history,
                             node.max_chars,
                             &node.filter,
                         );
                     }
                 }
                 TextInputAction::Copy => {
-                    if let Some(text) = editor.copy_selection() {
+                    if let Some(text) = copy_selections(&editor, carets) {
                         let _ = clipboard.set_text(text);
                     }
                 }
@@ -581,9 +792,11 @@ pub fn process_text_input_queues(
                 TextInputAction::PasteDeferred(mut clipboard_read) => {
                     if let Some(text) = clipboard_read.poll_result() {
                         if let Ok(text) = text {
-                            apply_text_input_edit(
+                            change = apply_text_input_edit_at_carets(
                                 TextInputEdit::Paste(text),
                                 &mut editor,
+                                carets,
+                                &mut history,
                                 node.max_chars,
                                 &node.filter,
                             );
@@ -594,35 +807,263 @@ pub fn process_text_input_queues(
                         break;
                     }
                 }
+                TextInputAction::SetStyle { range, style } => {
+                    editor.with_buffer_mut(|buffer| span_styles.set(buffer, range, style));
+                    // the span's attributes are resolved when the buffer is next updated
+                    *needs_update = true;
+                }
+                TextInputAction::Edit(text_input_edit)
+                    if display_text.is_some() && text_input_edit.pointer_position().is_some() =>
+                {
+                    // pointer edits on inputs showing a `DisplayText` are hit tested against the displayed text
+                    if let Some(position) = text_input_edit.pointer_position()
+                        && let Some(cursor) = display_hit(&editor, display_text.as_ref(), position)
+                    {
+                        if !carets.is_empty() {
+                            carets.clear();
+                        }
+                        apply_pointer_edit(&text_input_edit, &mut editor, cursor);
+                        history.seal();
+                    }
+                }
                 TextInputAction::Edit(text_input_edit) => {
-                    apply_text_input_edit(
+                    change = apply_text_input_edit_at_carets(
                         text_input_edit,
                         &mut editor,
+                        carets,
+                        &mut history,
                         node.max_chars,
                         &node.filter,
                     );
                 }
+                TextInputAction::Kill(edit) => {
+                    if !carets.is_empty() {
+                        carets.clear();
+                        editor.set_redraw(true);
+                    }
+                    let before = editor.with_buffer(crate::get_text);
+                    let offset =
+                        editor.with_buffer(|buffer| byte_offset_at_cursor(buffer, editor.cursor()));
+                    change = apply_text_input_edit(
+                        edit,
+                        &mut editor,
+                        &mut history,
+                        node.max_chars,
+                        &node.filter,
+                    );
+                    let after_len = editor.with_buffer(crate::get_text).len();
+                    let killed_at = selected_range(&editor).start;
+                    // the contents of password inputs aren't kept
+                    if node.mode.mask().is_none()
+                        && let Some(killed) = before
+                            .get(killed_at..killed_at + before.len().saturating_sub(after_len))
+                    {
+                        kill_ring.kill(entity, offset, killed_at, killed.to_string());
+                    }
+                }
+                TextInputAction::Yank => {
+                    let Some(text) = kill_ring.yank().map(str::to_string) else {
+                        continue;
+                    };
+                    let start = selected_range(&editor).start;
+                    change = apply_text_input_edit_at_carets(
+                        TextInputEdit::Paste(text),
+                        &mut editor,
+                        carets,
+                        &mut history,
+                        node.max_chars,
+                        &node.filter,
+                    );
+                    if carets.is_empty() {
+                        let end = selected_range(&editor).end;
+                        kill_ring.yanked(entity, start..end, 0);
+                    }
+                }
+                TextInputAction::YankPop => {
+                    let offset = selected_range(&editor).end;
+                    let Some((range, index, text)) = kill_ring.yank_pop(entity, offset) else {
+                        continue;
+                    };
+                    let start = range.start;
+                    change = apply_text_input_edit(
+                        TextInputEdit::ReplaceRange {
+                            range,
+                            text: text.to_string(),
+                        },
+                        &mut editor,
+                        &mut history,
+                        node.max_chars,
+                        &node.filter,
+                    );
+                    let end = selected_range(&editor).end;
+                    kill_ring.yanked(entity, start..end, index);
+                }
+                TextInputAction::Find { query, options } => {
+                    let search = search.insert(TextInputSearch::new(query, options));
+                    update_search(search, &editor, node);
+                    editor.set_redraw(true);
+                }
+                TextInputAction::ClearSearch => {
+                    if search.take().is_some() {
+                        editor.set_redraw(true);
+                    }
+                }
+                TextInputAction::FindNext
+                | TextInputAction::FindPrevious
+                | TextInputAction::Replace(_)
+                | TextInputAction::ReplaceAll(_) => {
+                    let Some(search) = search.as_mut() else {
+                        continue;
+                    };
+                    if !carets.is_empty() {
+                        // the found match replaces the carets
+                        carets.clear();
+                        editor.set_redraw(true);
+                    }
+                    update_search(search, &editor, node);
+                    let selected = selected_range(&editor);
+                    let found = match action {
+                        TextInputAction::FindPrevious => search.previous_match(selected.start),
+                        TextInputAction::Replace(text) => {
+                            if search.matches().contains(&selected) {
+                                let text = search.replacement(&selected, &text);
+                                change = apply_text_input_edit(
+                                    TextInputEdit::ReplaceRange {
+                                        range: selected,
+                                        text,
+                                    },
+                                    &mut editor,
+                                    &mut history,
+                                    node.max_chars,
+                                    &node.filter,
+                                );
+                                update_search(search, &editor, node);
+                            }
+                            search.next_match(selected_range(&editor).end)
+                        }
+                        TextInputAction::ReplaceAll(text) => {
+                            let replacements = search
+                                .matches()
+                                .iter()
+                                .map(|range| (range.clone(), search.replacement(range, &text)))
+                                .collect();
+                            change = apply_text_input_edit(
+                                TextInputEdit::ReplaceRanges(replacements),
+                                &mut editor,
+                                &mut history,
+                                node.max_chars,
+                                &node.filter,
+                            );
+                            update_search(search, &editor, node);
+                            None
+                        }
+                        _ => search.next_match(selected.end),
+                    };
+                    if let Some(range) = found {
+                        let (start, end) = editor.with_buffer(|buffer| {
+                            (
+                                cursor_at_byte_offset(buffer, range.start),
+                                cursor_at_byte_offset(buffer, range.end),
+                            )
+                        });
+                        editor.set_selection(Selection::Normal(start));
+                        editor.set_cursor(end);
+                    }
+                }
+            }
+            if let Some(change) = change {
+                *text_generation += 1;
+                let new_text = editor.with_buffer(crate::get_text);
+                let (old_text, delta) = TextDelta::from_change(&change, &new_text);
+                let event = TextChangedEvent {
+                    entity,
+                    old_text,
+                    new_text,
+                    delta,
+                };
+                commands.trigger_targets(event.clone(), entity);
+                changed_writer.write(event);
             }
         }
     }
 }
 
+/// Finds the matches of a search after the text changes. Password inputs are never searched.
+fn update_search(
+    search: &mut TextInputSearch,
+    editor: &BorrowedWithFontSystem<Editor<'static>>,
+    input: &TextInputNode,
+) {
+    if input.mode.mask().is_none() {
+        search.update(&editor.with_buffer(crate::get_text));
+    }
+}
+
+/// Byte range of the selected text, or an empty range at the cursor if nothing is selected
+fn selected_range(editor: &Editor<'static>) -> std::ops::Range<usize> {
+    editor.with_buffer(|buffer| {
+        let (start, end) = editor
+            .selection_bounds()
+            .unwrap_or((editor.cursor(), editor.cursor()));
+        byte_offset_at_cursor(buffer, start)..byte_offset_at_cursor(buffer, end)
+    })
+}
+
 pub fn on_focused_keyboard_input(
-    trigger: Trigger<FocusedInput<KeyboardInput>>,
-    mut query: Query<(&TextInputNode, &mut TextInputQueue)>,
+    mut trigger: Trigger<FocusedInput<KeyboardInput>>,
+    mut query: Query<(
+        &TextInputNode,
+        &mut TextInputQueue,
+        Option<&TextInputKeymap>,
+    )>,
+    keymap: Res<TextInputKeymap>,
     mut global_state: ResMut<TextInputGlobalState>,
+    tab_navigation: TabNavigation,
+    mut input_focus: ResMut<InputFocus>,
+    mut input_focus_visible: ResMut<InputFocusVisible>,
 ) {
-    if let Ok((input, mut queue)) = query.get_mut(trigger.target()) {
-        let TextInputGlobalState {
-            shift,
-            overwrite_mode,
-            command,
-        } = &mut *global_state;
+    if let Ok((input, mut queue, input_keymap)) = query.get_mut(trigger.target()) {
+        let keyboard_input = &trigger.event().input;
+        if input.tab_navigation
+            && keyboard_input.logical_key == Key::Tab
+            && keyboard_input.state == ButtonState::Pressed
+        {
+            let modifiers = global_state.modifiers;
+            if modifiers.ctrl {
+                queue.add(TextInputAction::Edit(TextInputEdit::Insert(
+                    '\t',
+                    global_state.overwrite_mode,
+                )));
+            } else {
+                let action = if modifiers.shift {
+                    NavAction::Previous
+                } else {
+                    NavAction::Next
+                };
+                match tab_navigation.navigate(&input_focus, action) {
+                    Ok(next)
+                    | Err(TabNavigationError::NoTabGroupForCurrentFocus {
+                        new_focus: next, ..
+                    }) => {
+                        input_focus.set(next);
+                        input_focus_visible.0 = true;
+                    }
+                    Err(error) => warn!("Tab navigation error: {error}"),
+                }
+            }
+            // stop `handle_tab_navigation` from moving the focus again
+            trigger.propagate(false);
+            return;
+        }
+
+        let keymaps = input_keymap
+            .into_iter()
+            .chain([&*keymap])
+            .collect::<Vec<_>>();
         queue_text_input_action(
+            &keymaps,
             &input.mode,
-            shift,
-            overwrite_mode,
-            command,
+            &mut global_state,
             &trigger.event().input,
             |action| {
                 queue.add(action);
@@ -633,79 +1074,260 @@ pub fn on_focused_keyboard_input(
 
 pub fn listen_ime_events(
     mut ime_events: EventReader<Ime>,
-    mut text_inputs: Query<&mut TextInputQueue, With<TextInputNode>>,
+    mut text_inputs: Query<
+        (
+            Entity,
+            &mut TextInputQueue,
+            &mut TextInputBuffer,
+            &ComputedNodeTarget,
+        ),
+        With<TextInputNode>,
+    >,
     mut global_state: ResMut<TextInputGlobalState>,
     input_focus: Res<InputFocus>,
+    node_windows: NodeWindows,
 ) {
+    if input_focus.is_changed() {
+        // Discard any compositions in inputs that have lost focus
+        for (entity, _, mut buffer, _) in text_inputs.iter_mut() {
+            if input_focus.get() != Some(entity) && buffer.preedit.is_some() {
+                buffer.set_preedit(None);
+            }
+        }
+    }
+
     for event in ime_events.read() {
         // IME events are sent to windows, but we need the focused text input entity
         let Some(focused_entity) = input_focus.get() else {
             continue;
         };
-        
-        let Ok(mut queue) = text_inputs.get_mut(focused_entity) else {
+
+        let Ok((_, mut queue, mut buffer, target)) = text_inputs.get_mut(focused_entity) else {
             continue;
         };
 
+        // Ignore events from windows other than the one displaying the focused input
+        let (Ime::Commit { window, .. }
+        | Ime::Preedit { window, .. }
+        | Ime::Enabled { window }
+        | Ime::Disabled { window }) = event;
+        if node_windows.get(target) != Some(*window) {
+            continue;
+        }
+
         let TextInputGlobalState { overwrite_mode, .. } = &mut *global_state;
 
         match event {
-        Ime::Commit { value, .. } => {
-            // Handle committed text from IME (e.g., completed Chinese characters)
-            for character in value.chars() {
-                queue.add(TextInputAction::Edit(TextInputEdit::Insert(
-                    character,
-                    *overwrite_mode,
-                )));
+            Ime::Commit { value, .. } => {
+                // Handle committed text from IME (e.g., completed Chinese characters)
+                buffer.set_preedit(None);
+                for character in value.chars() {
+                    queue.add(TextInputAction::Edit(TextInputEdit::Insert(
+                        character,
+                        *overwrite_mode,
+                    )));
+                }
             }
-        }
-        Ime::Preedit { value, cursor, .. } => {
-            // Handle preedit text (composition text shown during IME input)
-            // For now, we log it for debugging, but in the future this could be
-            // shown as a temporary overlay or inline preview
-            if !value.is_empty() {
-                bevy::log::debug!("IME Preedit: '{}' (cursor: {:?})", value, cursor);
+            Ime::Preedit { value, cursor, .. } => {
+                // Composition text is displayed at the cursor but isn't added to the buffer until it is committed
+                let preedit = (!value.is_empty()).then(|| ImePreedit {
+                    text: value.clone(),
+                    cursor: *cursor,
+                });
+                buffer.set_preedit(preedit);
+            }
+            Ime::Enabled { .. } => {
+                bevy::log::debug!("IME Enabled for text input");
+            }
+            Ime::Disabled { .. } => {
+                buffer.set_preedit(None);
+                bevy::log::debug!("IME Disabled for text input");
             }
         }
-        Ime::Enabled { .. } => {
-            bevy::log::debug!("IME Enabled for text input");
+    }
+}
+
+/// Finds the window that a UI node is rendered to
+#[derive(SystemParam)]
+pub struct NodeWindows<'w, 's> {
+    cameras: Query<'w, 's, &'static Camera>,
+    primary_window: Query<'w, 's, Entity, With<PrimaryWindow>>,
+}
+
+impl NodeWindows<'_, '_> {
+    /// The window entity the node's camera renders to, if any
+    pub fn get(&self, target: &ComputedNodeTarget) -> Option<Entity> {
+        let camera = self.cameras.get(target.camera()?).ok()?;
+        match camera.target.normalize(self.primary_window.single().ok())? {
+            NormalizedRenderTarget::Window(window) => Some(window.entity()),
+            _ => None,
         }
-        Ime::Disabled { .. } => {
-            bevy::log::debug!("IME Disabled for text input");
+    }
+}
+
+/// Unfocuses the focused text input and clears its selection when the primary pointer button is pressed outside of it,
+/// if its `TextInputNode::unfocus_on_click_outside` is set
+pub fn unfocus_on_click_outside(
+    mut pressed_events: EventReader<Pointer<Pressed>>,
+    mut input_focus: ResMut<InputFocus>,
+    mut query: Query<(&TextInputNode, &mut TextInputBuffer)>,
+) {
+    let mut pressed_outside = false;
+    for event in pressed_events.read() {
+        if event.button != PointerButton::Primary {
+            continue;
         }
+        if input_focus.get() == Some(event.target) {
+            return;
         }
+        pressed_outside = true;
+    }
+
+    if !pressed_outside {
+        return;
     }
+
+    let Some(entity) = input_focus.get() else {
+        return;
+    };
+
+    let Ok((input, mut buffer)) = query.get_mut(entity) else {
+        return;
+    };
+
+    if !input.unfocus_on_click_outside {
+        return;
+    }
+
+    input_focus.clear();
+    buffer.carets.clear();
+    buffer.editor.set_selection(Selection::None);
+    buffer.editor.set_redraw(true);
 }
 
+/// Sends `TextInputFocused` and `TextInputBlurred` when the focused text input changes
+pub fn send_focus_events(
+    input_focus: Res<InputFocus>,
+    mut text_inputs: Query<(&TextInputNode, &mut TextInputQueue)>,
+    mut focused_input: Local<Option<Entity>>,
+    mut commands: Commands,
+    mut focused_writer: EventWriter<TextInputFocused>,
+    mut blurred_writer: EventWriter<TextInputBlurred>,
+) {
+    let focused = input_focus
+        .get()
+        .filter(|&entity| text_inputs.contains(entity));
+    if focused == *focused_input {
+        return;
+    }
+
+    // inputs that were despawned while focused aren't blurred
+    if let Some(entity) = focused_input.take()
+        && text_inputs.contains(entity)
+    {
+        commands.trigger_targets(TextInputBlurred { entity }, entity);
+        blurred_writer.write(TextInputBlurred { entity });
+    }
+
+    if let Some(entity) = focused {
+        if let Ok((input, mut queue)) = text_inputs.get_mut(entity)
+            && input.select_all_on_focus
+        {
+            queue.add(TextInputAction::Edit(TextInputEdit::SelectAll));
+        }
+        commands.trigger_targets(TextInputFocused { entity }, entity);
+        focused_writer.write(TextInputFocused { entity });
+    }
+    *focused_input = focused;
+}
+
+/// Allows IME on the window displaying the focused text input and disallows it everywhere else
 pub fn toggle_ime_on_focus(
     input_focus: Res<InputFocus>,
-    text_inputs: Query<&TextInputNode>,
-    // supporting multiple windows requires detecting
-    // on which window the text input is currently on.
-    // because IME is mostly useful on mobile,
-    // it's ok to only support single window for now.
-    window: Single<Entity, With<Window>>,
+    text_inputs: Query<(&TextInputNode, &ComputedNodeTarget)>,
+    node_windows: NodeWindows,
     winit_windows: NonSend<WinitWindows>,
-    mut ime_allowed: Local<bool>,
+    mut ime_window: Local<Option<Entity>>,
 ) {
-    if input_focus.is_changed() {
-        let is_text_input_focused = input_focus
-            .get()
-            .is_some_and(|focused_entity| text_inputs.get(focused_entity).is_ok());
-
-        if is_text_input_focused != *ime_allowed {
-            let Some(window) = winit_windows.get_window(*window) else {
-                warn!("no window found to toggle IME on");
-                return;
-            };
+    // IME is disabled for password and read-only inputs
+    let focused_window = input_focus
+        .get()
+        .and_then(|focused_entity| text_inputs.get(focused_entity).ok())
+        .filter(|(input, _)| input.mode.mask().is_none() && !input.is_read_only)
+        .and_then(|(_, target)| node_windows.get(target));
 
-            *ime_allowed = is_text_input_focused;
-            window.set_ime_allowed(*ime_allowed);
-            
-            // Note: IME cursor position could be set here with window.set_ime_cursor_area()
-            // but it requires proper coordinate conversion and winit types that may vary
-            // between Bevy versions. The IME will still work without explicit positioning,
-            // it will just appear at a default location determined by the OS.
-        }
+    if focused_window == *ime_window {
+        return;
+    }
+
+    if let Some(previous) = ime_window.take()
+        && let Some(window) = winit_windows.get_window(previous)
+    {
+        window.set_ime_allowed(false);
+    }
+
+    // If the window hasn't been created yet, `ime_window` is left unset and IME is allowed on a later frame
+    if let Some(entity) = focused_window
+        && let Some(window) = winit_windows.get_window(entity)
+    {
+        window.set_ime_allowed(true);
+        *ime_window = Some(entity);
+    }
+}
+
+/// Moves the IME candidate window to just below the text cursor of the focused text input
+#[allow(clippy::type_complexity)]
+pub fn update_ime_position(
+    input_focus: Res<InputFocus>,
+    text_inputs: Query<(
+        &TextInputNode,
+        &TextInputBuffer,
+        &ComputedNode,
+        &GlobalTransform,
+        &ComputedNodeTarget,
+        Option<&TextInputGutterLayout>,
+    )>,
+    node_windows: NodeWindows,
+    mut windows: Query<&mut Window>,
+) {
+    let Some((input, buffer, node, transform, target, gutter)) = input_focus
+        .get()
+        .and_then(|focused_entity| text_inputs.get(focused_entity).ok())
+    else {
+        return;
+    };
+
+    if input.mode.mask().is_some() {
+        return;
+    }
+
+    let Some(mut window) = node_windows
+        .get(target)
+        .and_then(|entity| windows.get_mut(entity).ok())
+    else {
+        return;
+    };
+
+    let Some((x, y)) = buffer.display_cursor_position() else {
+        return;
+    };
+
+    let scroll = buffer.display_scroll();
+    let line_height = buffer
+        .editor
+        .with_buffer(|buffer| buffer.metrics().line_height);
+    let rect = Rect::from_center_size(transform.translation().truncate(), node.size());
+
+    // `ime_position` is in logical pixels relative to the top left of the window
+    let position = (rect.min
+        + Vec2::new(
+            x as f32 - scroll.horizontal + gutter.map_or(0., |gutter| gutter.width),
+            y as f32 - scroll.vertical + line_height,
+        ))
+    .clamp(rect.min, rect.max)
+        * node.inverse_scale_factor();
+
+    if window.ime_position != position {
+        window.ime_position = position;
     }
 }
@@ -0,0 +1,305 @@
+use crate::DisplayText;
+use crate::TextInputGlyph;
+use crate::text_input_pipeline::TextInputPipeline;
+use crate::text_input_pipeline::indexed_layout_runs;
+use crate::text_input_pipeline::place_glyph;
+use bevy::asset::AssetId;
+use bevy::asset::Assets;
+use bevy::color::Color;
+use bevy::color::palettes::tailwind::GRAY_200;
+use bevy::color::palettes::tailwind::GRAY_500;
+use bevy::ecs::component::Component;
+use bevy::image::Image;
+use bevy::image::TextureAtlasLayout;
+use bevy::math::Rect;
+use bevy::prelude::ReflectComponent;
+use bevy::reflect::Reflect;
+use bevy::reflect::std_traits::ReflectDefault;
+use bevy::text::Font;
+use bevy::text::FontSmoothing;
+use bevy::text::TextError;
+use bevy::text::cosmic_text::Align;
+use bevy::text::cosmic_text::Attrs;
+use bevy::text::cosmic_text::Buffer;
+use bevy::text::cosmic_text::BufferLine;
+use bevy::text::cosmic_text::Cursor;
+use bevy::text::cosmic_text::FontSystem;
+use bevy::text::cosmic_text::Metrics;
+use bevy::text::cosmic_text::Shaping;
+use bevy::text::cosmic_text::Wrap;
+
+/// Which lines are numbered by a [`TextInputGutter`]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Reflect)]
+#[reflect(Default, Debug, PartialEq)]
+pub enum GutterNumbering {
+    /// Number the lines of the text, lines wrapped over several rows are numbered once
+    #[default]
+    Logical,
+    /// Number every row of the text, including the rows of wrapped lines
+    Visual,
+}
+
+/// Line number gutter shown to the left of the text of a text input.
+/// Optional component.
+///
+/// The gutter reduces the width available to the text.
+/// The number of the line containing the cursor is highlighted, and clicking a number selects its line.
+#[derive(Component, Copy, Clone, Debug, PartialEq, Reflect)]
+#[reflect(Component, Default, Debug, PartialEq)]
+#[require(TextInputGutterStyle, TextInputGutterLayout)]
+pub struct TextInputGutter {
+    pub numbering: GutterNumbering,
+    /// The gutter is always wide enough for numbers with at least this many digits
+    pub min_digits: usize,
+}
+
+impl Default for TextInputGutter {
+    fn default() -> Self {
+        Self {
+            numbering: GutterNumbering::Logical,
+            min_digits: 2,
+        }
+    }
+}
+
+impl TextInputGutter {
+    /// Number of digits the gutter needs space for
+    pub(crate) fn digits(&self, buffer: &Buffer) -> usize {
+        let count = match self.numbering {
+            GutterNumbering::Logical => buffer.lines.len(),
+            GutterNumbering::Visual => buffer
+                .lines
+                .iter()
+                .map(|line| line.layout_opt().map_or(1, |layout| layout.len()))
+                .sum(),
+        };
+        (count.max(1).ilog10() as usize + 1).max(self.min_digits)
+    }
+}
+
+#[derive(Component, Copy, Clone, Debug, PartialEq, Reflect)]
+#[reflect(Component, Default, Debug, PartialEq)]
+pub struct TextInputGutterStyle {
+    /// Space on each side of the line numbers in logical pixels
+    pub padding: f32,
+    pub background_color: Color,
+    pub number_color: Color,
+    /// Color of the number of the line containing the cursor
+    pub current_line_color: Color,
+    /// Background color behind the number of the line containing the cursor
+    pub current_line_background_color: Color,
+}
+
+impl Default for TextInputGutterStyle {
+    fn default() -> Self {
+        Self {
+            padding: 6.,
+            background_color: Color::NONE,
+            number_color: GRAY_500.into(),
+            current_line_color: GRAY_200.into(),
+            current_line_background_color: Color::NONE,
+        }
+    }
+}
+
+/// A line number shown in the gutter
+#[derive(Clone, Debug, PartialEq)]
+pub struct GutterNumber {
+    pub number: usize,
+    /// The buffer line the number belongs to
+    pub line: usize,
+    /// Start of the text selected by clicking the number
+    pub start: Cursor,
+    /// End of the text selected by clicking the number
+    pub end: Cursor,
+    /// The number's row of the gutter in physical pixels.
+    /// Like the text's layout, rows are positioned relative to the top of the first visible line, before vertical scrolling.
+    pub rect: Rect,
+}
+
+/// Gutter layout, updated by `text_input_system`
+#[derive(Component, Clone, Debug, Default)]
+pub struct TextInputGutterLayout {
+    /// Width of the gutter in physical pixels. The text is offset to the right by this width.
+    pub width: f32,
+    /// The visible line numbers
+    pub numbers: Vec<GutterNumber>,
+    /// Glyphs of the visible line numbers, `TextInputGlyph::line_index` is the buffer line of each number
+    pub glyphs: Vec<TextInputGlyph>,
+    pub(crate) digits: usize,
+}
+
+impl TextInputGutterLayout {
+    /// The number in the row at `y`, in the coordinates of `GutterNumber::rect`
+    pub fn number_at(&self, y: f32) -> Option<&GutterNumber> {
+        self.numbers
+            .iter()
+            .find(|number| number.rect.min.y <= y && y < number.rect.max.y)
+    }
+}
+
+/// Width of `digits` digits in pixels
+pub(crate) fn digits_width(
+    font_system: &mut FontSystem,
+    metrics: Metrics,
+    attrs: Attrs,
+    digits: usize,
+) -> f32 {
+    let mut buffer = Buffer::new(font_system, metrics);
+    buffer.set_size(font_system, None, None);
+    buffer.set_text(font_system, &"0".repeat(digits), attrs, Shaping::Advanced);
+    buffer
+        .layout_runs()
+        .map(|run| run.line_w)
+        .fold(0., f32::max)
+        .ceil()
+}
+
+/// Lays out the numbers of the visible lines of `layout_buffer`.
+/// `buffer` is the text input's buffer, and `display_text` its display text if it has one.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn update_gutter_layout(
+    gutter: &TextInputGutter,
+    layout: &mut TextInputGutterLayout,
+    padding: f32,
+    buffer: &Buffer,
+    layout_buffer: &Buffer,
+    display_text: Option<&DisplayText>,
+    font_id: AssetId<Font>,
+    font_smoothing: FontSmoothing,
+    text_input_pipeline: &mut TextInputPipeline,
+    texture_atlases: &mut Assets<TextureAtlasLayout>,
+    textures: &mut Assets<Image>,
+) -> Result<(), TextError> {
+    layout.numbers.clear();
+    layout.glyphs.clear();
+
+    let Some(attrs) = buffer
+        .lines
+        .first()
+        .map(|line| line.attrs_list().defaults())
+    else {
+        return Ok(());
+    };
+
+    let to_buffer_cursor = |cursor: Cursor| match display_text {
+        Some(display_text) => display_text.buffer_cursor(buffer, cursor),
+        None => cursor,
+    };
+    let line_end = |line: usize| {
+        if line + 1 < layout_buffer.lines.len() {
+            Cursor::new(line + 1, 0)
+        } else {
+            Cursor::new(line, layout_buffer.lines[line].text().len())
+        }
+    };
+
+    // number of visual rows above the current line
+    let scroll_line = layout_buffer.scroll().line;
+    let row_count = |line: &BufferLine| line.layout_opt().map_or(1, |layout| layout.len());
+    let mut rows_above = layout_buffer.lines[..scroll_line.min(layout_buffer.lines.len())]
+        .iter()
+        .map(row_count)
+        .sum::<usize>();
+    let mut counted_line = scroll_line;
+
+    let mut baselines = vec![];
+    for (layout_i, run) in indexed_layout_runs(layout_buffer) {
+        while counted_line < run.line_i {
+            rows_above += row_count(&layout_buffer.lines[counted_line]);
+            counted_line += 1;
+        }
+        let rows = row_count(&layout_buffer.lines[run.line_i]);
+        let (number, start, end) = match gutter.numbering {
+            GutterNumbering::Logical if layout_i == 0 => (
+                run.line_i + 1,
+                Cursor::new(run.line_i, 0),
+                line_end(run.line_i),
+            ),
+            GutterNumbering::Logical => continue,
+            GutterNumbering::Visual => {
+                let start = run.glyphs.iter().map(|glyph| glyph.start).min();
+                let end = if layout_i + 1 < rows {
+                    Cursor::new(
+                        run.line_i,
+                        run.glyphs.iter().map(|glyph| glyph.end).max().unwrap_or(0),
+                    )
+                } else {
+                    line_end(run.line_i)
+                };
+                (
+                    rows_above + layout_i + 1,
+                    Cursor::new(
+                        run.line_i,
+                        if layout_i == 0 { 0 } else { start.unwrap_or(0) },
+                    ),
+                    end,
+                )
+            }
+        };
+        let line = to_buffer_cursor(Cursor::new(run.line_i, 0)).line;
+        layout.numbers.push(GutterNumber {
+            number,
+            line,
+            start: to_buffer_cursor(start),
+            end: to_buffer_cursor(end),
+            rect: Rect::new(
+                0.,
+                run.line_top,
+                layout.width,
+                run.line_top + run.line_height,
+            ),
+        });
+        baselines.push(run.line_y);
+    }
+
+    if layout.numbers.is_empty() {
+        return Ok(());
+    }
+
+    let font_system = &mut text_input_pipeline.font_system;
+    let digits_width = (layout.width - 2. * padding).max(0.);
+    let mut numbers_buffer = Buffer::new(font_system, buffer.metrics());
+    numbers_buffer.set_wrap(font_system, Wrap::None);
+    numbers_buffer.set_size(font_system, Some(digits_width), None);
+    let text = layout
+        .numbers
+        .iter()
+        .map(|number| number.number.to_string())
+        .collect::<Vec<_>>()
+        .join("\n");
+    numbers_buffer.set_text(font_system, &text, attrs, Shaping::Advanced);
+    for line in numbers_buffer.lines.iter_mut() {
+        line.set_align(Some(Align::Right));
+    }
+    numbers_buffer.shape_until_scroll(font_system, false);
+
+    for (run, (number, line_y)) in numbers_buffer
+        .layout_runs()
+        .zip(layout.numbers.iter().zip(baselines))
+    {
+        for layout_glyph in run.glyphs {
+            let (atlas_info, mut position, size) = place_glyph(
+                layout_glyph,
+                line_y,
+                font_id,
+                font_smoothing,
+                text_input_pipeline,
+                texture_atlases,
+                textures,
+            )?;
+            position.x += padding;
+            layout.glyphs.push(TextInputGlyph {
+                position,
+                size: size.as_vec2(),
+                atlas_info,
+                span_index: 0,
+                byte_index: layout_glyph.start,
+                byte_length: layout_glyph.end - layout_glyph.start,
+                line_index: number.line,
+            });
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,387 @@
+use std::ops::Range;
+use std::sync::Arc;
+
+use bevy::color::Color;
+use bevy::color::palettes::css::GRAY;
+use bevy::color::palettes::css::LIGHT_GREEN;
+use bevy::color::palettes::css::LIGHT_SKY_BLUE;
+use bevy::color::palettes::css::ORCHID;
+use bevy::color::palettes::css::SANDY_BROWN;
+use bevy::ecs::change_detection::DetectChanges;
+use bevy::ecs::change_detection::DetectChangesMut;
+use bevy::ecs::component::Component;
+use bevy::ecs::system::Query;
+use bevy::text::cosmic_text::Edit;
+
+use crate::TextInputBuffer;
+
+/// Colored byte range of a line
+pub type HighlightSpan = (Range<usize>, Color);
+
+/// Highlights the text of a text input, see [`TextInputHighlight`].
+pub trait TextInputHighlighter: Send + Sync + 'static {
+    /// Colored byte ranges of a single line of text.
+    /// Lines are highlighted independently, and only lines that have changed are highlighted again after an edit.
+    fn highlight_line(&self, line: &str) -> Vec<HighlightSpan>;
+}
+
+impl<F> TextInputHighlighter for F
+where
+    F: Fn(&str) -> Vec<HighlightSpan> + Send + Sync + 'static,
+{
+    fn highlight_line(&self, line: &str) -> Vec<HighlightSpan> {
+        self(line)
+    }
+}
+
+#[derive(Debug, Default)]
+struct HighlightedLine {
+    text: String,
+    spans: Vec<HighlightSpan>,
+}
+
+/// Syntax highlighting for a text input.
+/// Optional component.
+///
+/// Highlight colors replace the `TextColor` of the text, but text with a `TextInputSpanStyle` color keeps its style's color.
+#[derive(Component)]
+pub struct TextInputHighlight {
+    highlighter: Arc<dyn TextInputHighlighter>,
+    lines: Vec<HighlightedLine>,
+    /// The `TextInputBuffer::text_generation` of the highlighted text
+    text_generation: u64,
+}
+
+impl core::fmt::Debug for TextInputHighlight {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("TextInputHighlight")
+            .field("lines", &self.lines)
+            .finish_non_exhaustive()
+    }
+}
+
+impl TextInputHighlight {
+    pub fn new(highlighter: impl TextInputHighlighter) -> Self {
+        Self {
+            highlighter: Arc::new(highlighter),
+            lines: vec![],
+            text_generation: 0,
+        }
+    }
+
+    /// Replace the highlighter, all the lines are highlighted again
+    pub fn set_highlighter(&mut self, highlighter: impl TextInputHighlighter) {
+        self.highlighter = Arc::new(highlighter);
+        self.lines.clear();
+    }
+
+    /// The colored spans of a line of the buffer
+    pub fn line_spans(&self, line: usize) -> &[HighlightSpan] {
+        self.lines
+            .get(line)
+            .map_or(&[], |line| line.spans.as_slice())
+    }
+
+    /// The highlight color of the text at a byte index of a line
+    pub fn color_at(&self, line: usize, index: usize) -> Option<Color> {
+        self.line_spans(line)
+            .iter()
+            .find(|(range, _)| range.contains(&index))
+            .map(|(_, color)| *color)
+    }
+
+    /// Highlight the lines that differ from the cached lines
+    fn update(&mut self, lines: &[&str]) {
+        let prefix = self
+            .lines
+            .iter()
+            .zip(lines)
+            .take_while(|(cached, line)| cached.text == **line)
+            .count();
+        let suffix = self.lines[prefix..]
+            .iter()
+            .rev()
+            .zip(lines[prefix..].iter().rev())
+            .take_while(|(cached, line)| cached.text == **line)
+            .count();
+
+        let highlighted = lines[prefix..lines.len() - suffix]
+            .iter()
+            .map(|line| HighlightedLine {
+                text: line.to_string(),
+                spans: self.highlighter.highlight_line(line),
+            })
+            .collect::<Vec<_>>();
+        let end = self.lines.len() - suffix;
+        self.lines.splice(prefix..end, highlighted);
+    }
+}
+
+/// Highlights the lines of text inputs changed since the last update
+pub fn highlight_text_inputs(mut query: Query<(&TextInputBuffer, &mut TextInputHighlight)>) {
+    for (buffer, mut highlight) in query.iter_mut() {
+        if highlight.text_generation == buffer.text_generation && !highlight.is_changed() {
+            continue;
+        }
+        let highlight = highlight.bypass_change_detection();
+        highlight.text_generation = buffer.text_generation;
+        buffer.editor.with_buffer(|buffer| {
+            let lines = buffer
+                .lines
+                .iter()
+                .map(|line| line.text())
+                .collect::<Vec<_>>();
+            highlight.update(&lines);
+        });
+    }
+}
+
+/// Colors used by [`KeywordHighlighter`]
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SyntaxColors {
+    pub keyword: Color,
+    pub string: Color,
+    pub number: Color,
+    pub comment: Color,
+    /// Field names and object keys, identifiers or strings followed by a `:`
+    pub field: Color,
+}
+
+impl Default for SyntaxColors {
+    fn default() -> Self {
+        Self {
+            keyword: ORCHID.into(),
+            string: LIGHT_GREEN.into(),
+            number: SANDY_BROWN.into(),
+            comment: GRAY.into(),
+            field: LIGHT_SKY_BLUE.into(),
+        }
+    }
+}
+
+/// Simple highlighter for keywords, double quoted strings, numbers, line comments and field names
+#[derive(Clone, Debug, Default)]
+pub struct KeywordHighlighter {
+    pub keywords: Vec<String>,
+    /// Prefix starting a comment that continues to the end of the line
+    pub line_comment: Option<String>,
+    pub colors: SyntaxColors,
+}
+
+impl KeywordHighlighter {
+    pub fn new(keywords: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self {
+            keywords: keywords.into_iter().map(Into::into).collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Highlighter for JSON
+    pub fn json() -> Self {
+        Self::new(["true", "false", "null"])
+    }
+
+    /// Highlighter for RON
+    pub fn ron() -> Self {
+        Self::new(["true", "false", "Some", "None"]).with_line_comment("//")
+    }
+
+    pub fn with_line_comment(mut self, prefix: impl Into<String>) -> Self {
+        self.line_comment = Some(prefix.into());
+        self
+    }
+
+    pub fn with_colors(mut self, colors: SyntaxColors) -> Self {
+        self.colors = colors;
+        self
+    }
+}
+
+impl TextInputHighlighter for KeywordHighlighter {
+    fn highlight_line(&self, line: &str) -> Vec<HighlightSpan> {
+        let mut spans = vec![];
+        let is_field = |end: usize| line[end..].trim_start().starts_with(':');
+        let mut chars = line.char_indices().peekable();
+        while let Some((start, c)) = chars.next() {
+            if let Some(prefix) = &self.line_comment
+                && !prefix.is_empty()
+                && line[start..].starts_with(prefix.as_str())
+            {
+                spans.push((start..line.len(), self.colors.comment));
+                break;
+            }
+
+            if c == '"' {
+                let mut end = line.len();
+                let mut escaped = false;
+                for (index, c) in chars.by_ref() {
+                    match c {
+                        '\\' if !escaped => escaped = true,
+                        '"' if !escaped => {
+                            end = index + 1;
+                            break;
+                        }
+                        _ => escaped = false,
+                    }
+                }
+                let color = if is_field(end) {
+                    self.colors.field
+                } else {
+                    self.colors.string
+                };
+                spans.push((start..end, color));
+            } else if c.is_ascii_digit() {
+                let mut end = start + 1;
+                while let Some(&(index, c)) = chars.peek() {
+                    if !(c.is_ascii_alphanumeric() || c == '.' || c == '_') {
+                        break;
+                    }
+                    end = index + c.len_utf8();
+                    chars.next();
+                }
+                spans.push((start..end, self.colors.number));
+            } else if c.is_alphabetic() || c == '_' {
+                let mut end = start + c.len_utf8();
+                while let Some(&(index, c)) = chars.peek() {
+                    if !(c.is_alphanumeric() || c == '_') {
+                        break;
+                    }
+                    end = index + c.len_utf8();
+                    chars.next();
+                }
+                let word = &line[start..end];
+                if self.keywords.iter().any(|keyword| keyword == word) {
+                    spans.push((start..end, self.colors.keyword));
+                } else if is_field(end) {
+                    spans.push((start..end, self.colors.field));
+                }
+            }
+        }
+        spans
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Highlighter coloring each line in a single color, recording the lines it highlights
+    fn recorder() -> (impl TextInputHighlighter, Arc<Mutex<Vec<String>>>) {
+        let highlighted = Arc::new(Mutex::new(vec![]));
+        let recorded = highlighted.clone();
+        let highlighter = move |line: &str| {
+            recorded.lock().unwrap().push(line.to_string());
+            vec![(0..line.len(), GRAY.into())]
+        };
+        (highlighter, highlighted)
+    }
+
+    fn take(highlighted: &Mutex<Vec<String>>) -> Vec<String> {
+        std::mem::take(&mut *highlighted.lock().unwrap())
+    }
+
+    #[test]
+    fn only_changed_lines_are_highlighted_again() {
+        let (highlighter, highlighted) = recorder();
+        let mut highlight = TextInputHighlight::new(highlighter);
+        highlight.update(&["a", "b", "c"]);
+        assert_eq!(take(&highlighted), ["a", "b", "c"]);
+
+        highlight.update(&["a", "b", "c"]);
+        assert!(take(&highlighted).is_empty());
+
+        highlight.update(&["a", "bb", "c"]);
+        assert_eq!(take(&highlighted), ["bb"]);
+
+        // inserted in the middle
+        highlight.update(&["a", "bb", "x", "y", "c"]);
+        assert_eq!(take(&highlighted), ["x", "y"]);
+        assert_eq!(highlight.line_spans(4), [(0..1, GRAY.into())]);
+
+        // removed from the middle
+        highlight.update(&["a", "y", "c"]);
+        assert!(take(&highlighted).is_empty());
+        assert_eq!(highlight.lines.len(), 3);
+        assert_eq!(highlight.lines[1].text, "y");
+
+        // repeated lines
+        highlight.update(&["a", "a", "y", "c"]);
+        assert_eq!(take(&highlighted), ["a"]);
+
+        highlight.update(&[]);
+        assert!(highlight.lines.is_empty());
+        assert!(highlight.line_spans(0).is_empty());
+    }
+
+    #[test]
+    fn set_highlighter_highlights_every_line_again() {
+        let (highlighter, _) = recorder();
+        let mut highlight = TextInputHighlight::new(highlighter);
+        highlight.update(&["a", "b"]);
+        let (highlighter, highlighted) = recorder();
+        highlight.set_highlighter(highlighter);
+        highlight.update(&["a", "b"]);
+        assert_eq!(take(&highlighted), ["a", "b"]);
+    }
+
+    fn spans(highlighter: &KeywordHighlighter, line: &str) -> Vec<(String, Color)> {
+        highlighter
+            .highlight_line(line)
+            .into_iter()
+            .map(|(range, color)| (line[range].to_string(), color))
+            .collect()
+    }
+
+    #[test]
+    fn keyword_highlighter_tokens() {
+        let highlighter = KeywordHighlighter::ron();
+        let colors = SyntaxColors::default();
+        let span = |text: &str, color| (text.to_string(), color);
+
+        assert_eq!(
+            spans(&highlighter, r#"Some("a\"b") None x"#),
+            [
+                span("Some", colors.keyword),
+                span(r#""a\"b""#, colors.string),
+                span("None", colors.keyword),
+            ]
+        );
+        // an escaped backslash doesn't escape the closing quote
+        assert_eq!(
+            spans(&highlighter, r#""a\\" 1"#),
+            [span(r#""a\\""#, colors.string), span("1", colors.number)]
+        );
+        // unterminated strings continue to the end of the line
+        assert_eq!(
+            spans(&highlighter, r#""abc, 1"#),
+            [span(r#""abc, 1"#, colors.string)]
+        );
+        assert_eq!(
+            spans(&highlighter, r#"name: "x", "key" : true"#),
+            [
+                span("name", colors.field),
+                span(r#""x""#, colors.string),
+                span(r#""key""#, colors.field),
+                span("true", colors.keyword),
+            ]
+        );
+        assert_eq!(
+            spans(&highlighter, "12 1.5e3_0 x1 _a"),
+            [span("12", colors.number), span("1.5e3_0", colors.number)]
+        );
+        assert_eq!(
+            spans(&highlighter, r#"1 "//" // true "x""#),
+            [
+                span("1", colors.number),
+                span(r#""//""#, colors.string),
+                span(r#"// true "x""#, colors.comment),
+            ]
+        );
+        // JSON has no line comments
+        assert_eq!(
+            spans(&KeywordHighlighter::json(), "// null"),
+            [span("null", colors.keyword)]
+        );
+    }
+}
@@ -0,0 +1,242 @@
+use std::collections::VecDeque;
+
+use bevy::ecs::component::Component;
+use bevy::text::cosmic_text::BorrowedWithFontSystem;
+use bevy::text::cosmic_text::Change;
+use bevy::text::cosmic_text::Cursor;
+use bevy::text::cosmic_text::Edit;
+use bevy::text::cosmic_text::Editor;
+use bevy::text::cosmic_text::Selection;
+
+/// Default maximum number of entries kept in a [`TextInputHistory`]
+pub const DEFAULT_HISTORY_DEPTH: usize = 100;
+
+/// The kind of edit recorded by a [`HistoryEntry`], used to decide which entries can be merged.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum EditKind {
+    /// Typed characters, consecutive inserts are merged into one entry
+    Insert,
+    /// Backspace or delete without a selection, consecutive deletes are merged into one entry
+    Delete,
+    /// Any other edit, never merged
+    Other,
+}
+
+/// A single undoable change along with the cursor and selection state around it
+#[derive(Clone, Debug)]
+pub struct HistoryEntry {
+    pub change: Change,
+    pub kind: EditKind,
+    pub cursor_before: Cursor,
+    pub selection_before: Selection,
+    pub cursor_after: Cursor,
+    pub selection_after: Selection,
+}
+
+/// Undo and redo history for a text input
+#[derive(Component, Debug)]
+pub struct TextInputHistory {
+    undo_stack: VecDeque<HistoryEntry>,
+    redo_stack: Vec<HistoryEntry>,
+    /// Maximum number of undo entries kept, the oldest entries are discarded first
+    pub max_depth: usize,
+    /// If false the next edit always starts a new entry
+    can_merge: bool,
+}
+
+impl Default for TextInputHistory {
+    fn default() -> Self {
+        Self::with_max_depth(DEFAULT_HISTORY_DEPTH)
+    }
+}
+
+impl TextInputHistory {
+    /// New history that keeps at most `max_depth` undo entries
+    pub fn with_max_depth(max_depth: usize) -> Self {
+        Self {
+            undo_stack: VecDeque::new(),
+            redo_stack: Vec::new(),
+            max_depth,
+            can_merge: false,
+        }
+    }
+
+    /// True if there is an edit that can be undone
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    /// True if there is an edit that can be redone
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    /// Remove all entries
+    pub fn clear(&mut self) {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+        self.can_merge = false;
+    }
+
+    /// Stop the next edit from being merged into the most recent entry
+    pub fn seal(&mut self) {
+        self.can_merge = false;
+    }
+
+    /// Record a new entry, merging it with the previous entry if both are consecutive edits of the same kind.
+    /// Clears the redo stack.
+    pub fn push(&mut self, entry: HistoryEntry) {
+        if entry.change.items.is_empty() {
+            return;
+        }
+
+        self.redo_stack.clear();
+
+        if self.can_merge
+            && entry.kind != EditKind::Other
+            && let Some(last) = self.undo_stack.back_mut()
+            && last.kind == entry.kind
+            && same_position(last.cursor_after, entry.cursor_before)
+            && entry.selection_before == Selection::None
+        {
+            last.change.items.extend(entry.change.items);
+            last.cursor_after = entry.cursor_after;
+            last.selection_after = entry.selection_after;
+            return;
+        }
+
+        self.can_merge = entry.kind != EditKind::Other;
+        self.undo_stack.push_back(entry);
+        while self.max_depth < self.undo_stack.len() {
+            self.undo_stack.pop_front();
+        }
+    }
+
+    /// Revert the most recent entry. Returns the change applied to the text, or `None` if there was nothing to undo.
+    pub fn undo(
+        &mut self,
+        editor: &mut BorrowedWithFontSystem<'_, Editor<'static>>,
+    ) -> Option<Change> {
+        let entry = self.undo_stack.pop_back()?;
+        let mut change = entry.change.clone();
+        change.reverse();
+        editor.apply_change(&change);
+        editor.set_cursor(entry.cursor_before);
+        editor.set_selection(entry.selection_before);
+        editor.set_redraw(true);
+        self.redo_stack.push(entry);
+        self.can_merge = false;
+        Some(change)
+    }
+
+    /// Reapply the most recently undone entry. Returns the change applied to the text, or `None` if there was nothing to redo.
+    pub fn redo(
+        &mut self,
+        editor: &mut BorrowedWithFontSystem<'_, Editor<'static>>,
+    ) -> Option<Change> {
+        let entry = self.redo_stack.pop()?;
+        let change = entry.change.clone();
+        editor.apply_change(&change);
+        editor.set_cursor(entry.cursor_after);
+        editor.set_selection(entry.selection_after);
+        editor.set_redraw(true);
+        self.undo_stack.push_back(entry);
+        self.can_merge = false;
+        Some(change)
+    }
+}
+
+fn same_position(a: Cursor, b: Cursor) -> bool {
+    a.line == b.line && a.index == b.index
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::text::cosmic_text::Buffer;
+    use bevy::text::cosmic_text::ChangeItem;
+    use bevy::text::cosmic_text::FontSystem;
+    use bevy::text::cosmic_text::Metrics;
+    use bevy::text::cosmic_text::fontdb;
+
+    /// An entry for typing `text` at `index` on the first line
+    fn insert_entry(index: usize, text: &str, kind: EditKind) -> HistoryEntry {
+        let start = Cursor::new(0, index);
+        let end = Cursor::new(0, index + text.len());
+        HistoryEntry {
+            change: Change {
+                items: vec![ChangeItem {
+                    start,
+                    end,
+                    text: text.to_string(),
+                    insert: true,
+                }],
+            },
+            kind,
+            cursor_before: start,
+            selection_before: Selection::None,
+            cursor_after: end,
+            selection_after: Selection::None,
+        }
+    }
+
+    #[test]
+    fn consecutive_typing_is_merged() {
+        let mut history = TextInputHistory::default();
+        history.push(insert_entry(0, "a", EditKind::Insert));
+        history.push(insert_entry(1, "b", EditKind::Insert));
+        history.push(insert_entry(2, "c", EditKind::Insert));
+        assert_eq!(history.undo_stack.len(), 1);
+        assert_eq!(history.undo_stack[0].change.items.len(), 3);
+        assert_eq!(history.undo_stack[0].cursor_after, Cursor::new(0, 3));
+    }
+
+    #[test]
+    fn typing_is_not_merged_after_seal_or_elsewhere() {
+        let mut history = TextInputHistory::default();
+        history.push(insert_entry(0, "a", EditKind::Insert));
+        history.seal();
+        history.push(insert_entry(1, "b", EditKind::Insert));
+        // not at the cursor left by the previous entry
+        history.push(insert_entry(0, "c", EditKind::Insert));
+        history.push(insert_entry(1, "d", EditKind::Other));
+        history.push(insert_entry(2, "e", EditKind::Other));
+        assert_eq!(history.undo_stack.len(), 5);
+    }
+
+    #[test]
+    fn oldest_entries_are_evicted_past_max_depth() {
+        let mut history = TextInputHistory::with_max_depth(2);
+        for (index, text) in ["a", "b", "c"].into_iter().enumerate() {
+            history.push(insert_entry(index, text, EditKind::Other));
+        }
+        let texts = history
+            .undo_stack
+            .iter()
+            .map(|entry| entry.change.items[0].text.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(texts, ["b", "c"]);
+    }
+
+    #[test]
+    fn new_edit_clears_redo() {
+        let mut font_system =
+            FontSystem::new_with_locale_and_db("en-US".to_string(), fontdb::Database::new());
+        let buffer = Buffer::new(&mut font_system, Metrics::new(20., 20.));
+        let mut editor = Editor::new(buffer);
+        let mut editor = editor.borrow_with(&mut font_system);
+        let mut history = TextInputHistory::default();
+
+        let entry = insert_entry(0, "a", EditKind::Insert);
+        editor.apply_change(&entry.change);
+        history.push(entry);
+
+        assert!(history.undo(&mut editor).is_some());
+        assert!(editor.with_buffer(|buffer| buffer.lines[0].text().is_empty()));
+        assert!(history.can_redo());
+
+        history.push(insert_entry(0, "b", EditKind::Insert));
+        assert!(!history.can_redo());
+        assert!(history.redo(&mut editor).is_none());
+    }
+}
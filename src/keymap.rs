@@ -0,0 +1,781 @@
+use bevy::ecs::component::Component;
+use bevy::ecs::resource::Resource;
+use bevy::input::keyboard::Key;
+use bevy::platform::collections::HashMap;
+use bevy::text::cosmic_text::Motion;
+
+use crate::TextInputMode;
+use crate::actions::TextInputAction;
+use crate::actions::TextInputEdit;
+
+/// Modifier keys held down as part of a [`KeyChord`]
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct KeyModifiers {
+    pub shift: bool,
+    pub ctrl: bool,
+    pub alt: bool,
+    /// The Command key on macOS, the Windows key on Windows
+    pub super_key: bool,
+}
+
+impl KeyModifiers {
+    pub const NONE: Self = Self {
+        shift: false,
+        ctrl: false,
+        alt: false,
+        super_key: false,
+    };
+
+    /// True if Ctrl or Super is held down, typed characters aren't inserted while they are
+    pub fn is_command(&self) -> bool {
+        self.ctrl || self.super_key
+    }
+}
+
+/// A key pressed along with modifier keys
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct KeyChord {
+    pub key: Key,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeyChord {
+    /// A key pressed without any modifiers
+    pub fn new(key: Key) -> Self {
+        Self {
+            key,
+            modifiers: KeyModifiers::NONE,
+        }
+    }
+
+    /// A character key pressed without any modifiers
+    pub fn char(c: char) -> Self {
+        Self::new(Key::Character(c.to_string().into()))
+    }
+
+    pub fn shift(mut self) -> Self {
+        self.modifiers.shift = true;
+        self
+    }
+
+    pub fn ctrl(mut self) -> Self {
+        self.modifiers.ctrl = true;
+        self
+    }
+
+    pub fn alt(mut self) -> Self {
+        self.modifiers.alt = true;
+        self
+    }
+
+    pub fn super_key(mut self) -> Self {
+        self.modifiers.super_key = true;
+        self
+    }
+
+    /// The chord of a pressed key. Characters pressed with Ctrl, Alt or Super are converted to lowercase,
+    /// so that shortcuts work with capslock on or Shift held down.
+    pub fn from_key(key: &Key, modifiers: KeyModifiers) -> Self {
+        let key = match key {
+            Key::Character(str) if modifiers.is_command() || modifiers.alt => {
+                Key::Character(str.to_lowercase().into())
+            }
+            key => key.clone(),
+        };
+        Self { key, modifiers }
+    }
+
+    fn without_shift(&self) -> Self {
+        let mut chord = self.clone();
+        chord.modifiers.shift = false;
+        chord
+    }
+}
+
+/// What a [`KeyChord`] does in a text input
+#[derive(Clone, Debug)]
+pub enum KeyBinding {
+    Copy,
+    Cut,
+    Paste,
+    Submit,
+    /// Apply an edit
+    Edit(TextInputEdit),
+    /// Move the cursor. If Shift is held down and the chord with Shift isn't bound itself, the motion extends the selection.
+    Motion(Motion),
+    /// Apply a deleting edit and add the deleted text to the kill ring, see `TextInputAction::Kill`
+    Kill(TextInputEdit),
+    /// Insert the most recently killed text
+    Yank,
+    /// Replace the text inserted by the previous yank with earlier killed text
+    YankPop,
+    /// Toggle between inserting and overwriting typed characters
+    ToggleOverwrite,
+    /// Does nothing. Used to unbind a chord bound by a lower priority keymap, or to stop a character from being typed.
+    Unbound,
+}
+
+/// Key bindings for text inputs.
+///
+/// As a resource, the keymap used by every text input. Defaults to the bindings for the current platform.
+/// As a component on a text input entity, its bindings take priority over the resource's bindings for that input.
+///
+/// Characters typed without a binding, and without Ctrl or Super held down, are inserted into the text.
+#[derive(Resource, Component, Clone, Debug)]
+pub struct TextInputKeymap {
+    bindings: HashMap<KeyChord, KeyBinding>,
+}
+
+impl Default for TextInputKeymap {
+    fn default() -> Self {
+        Self::platform_default()
+    }
+}
+
+impl TextInputKeymap {
+    /// A keymap without any bindings
+    pub fn empty() -> Self {
+        Self {
+            bindings: HashMap::default(),
+        }
+    }
+
+    /// The bindings for the current platform, `macos` on macOS and `windows_linux` everywhere else
+    pub fn platform_default() -> Self {
+        if cfg!(target_os = "macos") {
+            Self::macos()
+        } else {
+            Self::windows_linux()
+        }
+    }
+
+    /// Navigation and editing keys shared by every preset
+    fn common() -> Self {
+        Self::empty()
+            .with(
+                KeyChord::new(Key::ArrowLeft),
+                KeyBinding::Motion(Motion::Left),
+            )
+            .with(
+                KeyChord::new(Key::ArrowRight),
+                KeyBinding::Motion(Motion::Right),
+            )
+            .with(KeyChord::new(Key::ArrowUp), KeyBinding::Motion(Motion::Up))
+            .with(
+                KeyChord::new(Key::ArrowDown),
+                KeyBinding::Motion(Motion::Down),
+            )
+            .with(KeyChord::new(Key::Home), KeyBinding::Motion(Motion::Home))
+            .with(KeyChord::new(Key::End), KeyBinding::Motion(Motion::End))
+            .with(
+                KeyChord::new(Key::PageUp),
+                KeyBinding::Motion(Motion::PageUp),
+            )
+            .with(
+                KeyChord::new(Key::PageDown),
+                KeyBinding::Motion(Motion::PageDown),
+            )
+            .with(
+                KeyChord::new(Key::Backspace),
+                KeyBinding::Edit(TextInputEdit::Backspace),
+            )
+            .with(
+                KeyChord::new(Key::Backspace).shift(),
+                KeyBinding::Edit(TextInputEdit::Backspace),
+            )
+            .with(
+                KeyChord::new(Key::Escape).shift(),
+                KeyBinding::Edit(TextInputEdit::Escape),
+            )
+            .with(
+                KeyChord::new(Key::Delete),
+                KeyBinding::Edit(TextInputEdit::Delete),
+            )
+            .with(KeyChord::new(Key::Delete).shift(), KeyBinding::Cut)
+            .with(
+                KeyChord::new(Key::Enter),
+                KeyBinding::Edit(TextInputEdit::Enter),
+            )
+            .with(KeyChord::new(Key::Enter).shift(), KeyBinding::Submit)
+            .with(
+                KeyChord::new(Key::Escape),
+                KeyBinding::Edit(TextInputEdit::Escape),
+            )
+            .with(
+                KeyChord::new(Key::Tab),
+                KeyBinding::Edit(TextInputEdit::Indent),
+            )
+            .with(
+                KeyChord::new(Key::Tab).shift(),
+                KeyBinding::Edit(TextInputEdit::Unindent),
+            )
+            .with(KeyChord::new(Key::Insert), KeyBinding::ToggleOverwrite)
+    }
+
+    /// Clipboard, undo and select all shortcuts, with word and buffer motions, using the given modifier
+    fn shortcuts(self, command: fn(KeyChord) -> KeyChord) -> Self {
+        self.with(command(KeyChord::char('c')), KeyBinding::Copy)
+            .with(command(KeyChord::char('x')), KeyBinding::Cut)
+            .with(command(KeyChord::char('v')), KeyBinding::Paste)
+            .with(
+                command(KeyChord::char('z')),
+                KeyBinding::Edit(TextInputEdit::Undo),
+            )
+            .with(
+                command(KeyChord::char('y')),
+                KeyBinding::Edit(TextInputEdit::Redo),
+            )
+            .with(
+                command(KeyChord::char('a')),
+                KeyBinding::Edit(TextInputEdit::SelectAll),
+            )
+            .with(
+                command(KeyChord::new(Key::ArrowLeft)),
+                KeyBinding::Motion(Motion::PreviousWord),
+            )
+            .with(
+                command(KeyChord::new(Key::ArrowRight)),
+                KeyBinding::Motion(Motion::NextWord),
+            )
+            .with(
+                command(KeyChord::new(Key::ArrowUp)),
+                KeyBinding::Edit(TextInputEdit::Scroll { lines: -1 }),
+            )
+            .with(
+                command(KeyChord::new(Key::ArrowDown)),
+                KeyBinding::Edit(TextInputEdit::Scroll { lines: 1 }),
+            )
+            .with(
+                command(KeyChord::new(Key::Home)),
+                KeyBinding::Motion(Motion::BufferStart),
+            )
+            .with(
+                command(KeyChord::new(Key::End)),
+                KeyBinding::Motion(Motion::BufferEnd),
+            )
+    }
+
+    /// Standard Windows and Linux bindings, shortcuts use Ctrl.
+    ///
+    /// Ctrl+Backspace and Ctrl+Delete delete words, and Ctrl+Shift+Backspace and Ctrl+Shift+Delete delete to the start and end of the line.
+    pub fn windows_linux() -> Self {
+        Self::common()
+            .shortcuts(KeyChord::ctrl)
+            .with(
+                KeyChord::new(Key::Backspace).ctrl(),
+                KeyBinding::Edit(TextInputEdit::DeleteWordBackward),
+            )
+            .with(
+                KeyChord::new(Key::Delete).ctrl(),
+                KeyBinding::Edit(TextInputEdit::DeleteWordForward),
+            )
+            .with(
+                KeyChord::new(Key::Backspace).ctrl().shift(),
+                KeyBinding::Edit(TextInputEdit::DeleteToLineStart),
+            )
+            .with(
+                KeyChord::new(Key::Delete).ctrl().shift(),
+                KeyBinding::Edit(TextInputEdit::DeleteToLineEnd),
+            )
+    }
+
+    /// Standard macOS bindings, shortcuts use Command.
+    ///
+    /// Command+Left and Right move to the start and end of the line, Command+Up and Down to the start and end of the text,
+    /// Option+Left and Right move by words, Option+Backspace and Option+Delete delete words,
+    /// Command+Backspace and Command+Delete delete to the start and end of the line,
+    /// and Command+Shift+Z redoes. Also includes the Emacs style Ctrl bindings supported by macOS text fields,
+    /// see [`TextInputKeymap::emacs`].
+    pub fn macos() -> Self {
+        Self::common()
+            .shortcuts(KeyChord::super_key)
+            .emacs_ctrl_keys()
+            .with(
+                KeyChord::char('z').super_key().shift(),
+                KeyBinding::Edit(TextInputEdit::Redo),
+            )
+            .with(
+                KeyChord::new(Key::ArrowLeft).super_key(),
+                KeyBinding::Motion(Motion::Home),
+            )
+            .with(
+                KeyChord::new(Key::ArrowRight).super_key(),
+                KeyBinding::Motion(Motion::End),
+            )
+            .with(
+                KeyChord::new(Key::ArrowUp).super_key(),
+                KeyBinding::Motion(Motion::BufferStart),
+            )
+            .with(
+                KeyChord::new(Key::ArrowDown).super_key(),
+                KeyBinding::Motion(Motion::BufferEnd),
+            )
+            .with(
+                KeyChord::new(Key::ArrowLeft).alt(),
+                KeyBinding::Motion(Motion::PreviousWord),
+            )
+            .with(
+                KeyChord::new(Key::ArrowRight).alt(),
+                KeyBinding::Motion(Motion::NextWord),
+            )
+            .with(
+                KeyChord::new(Key::Backspace).super_key(),
+                KeyBinding::Edit(TextInputEdit::DeleteToLineStart),
+            )
+            .with(
+                KeyChord::new(Key::Delete).super_key(),
+                KeyBinding::Edit(TextInputEdit::DeleteToLineEnd),
+            )
+            .with(
+                KeyChord::new(Key::Backspace).alt(),
+                KeyBinding::Edit(TextInputEdit::DeleteWordBackward),
+            )
+            .with(
+                KeyChord::new(Key::Delete).alt(),
+                KeyBinding::Edit(TextInputEdit::DeleteWordForward),
+            )
+    }
+
+    /// Emacs style bindings on top of the Windows and Linux bindings.
+    ///
+    /// Ctrl+F, B, N and P move the cursor right, left, down and up, Ctrl+A and E move to the start and end of the line,
+    /// Alt+F and B move by words, Alt+< and > move to the start and end of the text, Ctrl+D deletes and Ctrl+H backspaces.
+    /// Ctrl+K kills the rest of the line, Alt+D and Alt+Backspace kill the next and previous words, Ctrl+Y yanks the last killed text and Alt+Y replaces the yanked text with
+    /// earlier killed text, see [`TextInputKillRing`](crate::kill_ring::TextInputKillRing).
+    /// Ctrl+A no longer selects all.
+    pub fn emacs() -> Self {
+        Self::windows_linux()
+            .emacs_ctrl_keys()
+            .with(
+                KeyChord::char('f').alt(),
+                KeyBinding::Motion(Motion::NextWord),
+            )
+            .with(
+                KeyChord::char('b').alt(),
+                KeyBinding::Motion(Motion::PreviousWord),
+            )
+            .with(
+                KeyChord::char('<').alt(),
+                KeyBinding::Motion(Motion::BufferStart),
+            )
+            .with(
+                KeyChord::char('>').alt(),
+                KeyBinding::Motion(Motion::BufferEnd),
+            )
+            // Typing < and > needs Shift on most layouts, bound so that the motions don't extend the selection
+            .with(
+                KeyChord::char('<').alt().shift(),
+                KeyBinding::Motion(Motion::BufferStart),
+            )
+            .with(
+                KeyChord::char('>').alt().shift(),
+                KeyBinding::Motion(Motion::BufferEnd),
+            )
+            .with(
+                KeyChord::char('d').alt(),
+                KeyBinding::Kill(TextInputEdit::DeleteWordForward),
+            )
+            .with(
+                KeyChord::new(Key::Backspace).alt(),
+                KeyBinding::Kill(TextInputEdit::DeleteWordBackward),
+            )
+            .with(KeyChord::char('y').alt(), KeyBinding::YankPop)
+    }
+
+    /// Emacs style cursor movement, deletion, kill and yank bindings using Ctrl
+    fn emacs_ctrl_keys(self) -> Self {
+        self.with(
+            KeyChord::char('f').ctrl(),
+            KeyBinding::Motion(Motion::Right),
+        )
+        .with(KeyChord::char('b').ctrl(), KeyBinding::Motion(Motion::Left))
+        .with(KeyChord::char('n').ctrl(), KeyBinding::Motion(Motion::Down))
+        .with(KeyChord::char('p').ctrl(), KeyBinding::Motion(Motion::Up))
+        .with(KeyChord::char('a').ctrl(), KeyBinding::Motion(Motion::Home))
+        .with(KeyChord::char('e').ctrl(), KeyBinding::Motion(Motion::End))
+        .with(
+            KeyChord::char('d').ctrl(),
+            KeyBinding::Edit(TextInputEdit::Delete),
+        )
+        .with(
+            KeyChord::char('h').ctrl(),
+            KeyBinding::Edit(TextInputEdit::Backspace),
+        )
+        .with(
+            KeyChord::char('k').ctrl(),
+            KeyBinding::Kill(TextInputEdit::DeleteToLineEnd),
+        )
+        .with(KeyChord::char('y').ctrl(), KeyBinding::Yank)
+    }
+
+    /// Bind a chord, replacing any previous binding
+    pub fn bind(&mut self, chord: KeyChord, binding: KeyBinding) -> &mut Self {
+        self.bindings.insert(chord, binding);
+        self
+    }
+
+    /// Bind a chord, replacing any previous binding
+    pub fn with(mut self, chord: KeyChord, binding: KeyBinding) -> Self {
+        self.bind(chord, binding);
+        self
+    }
+
+    /// Bind a chord to [`KeyBinding::Unbound`], so that it does nothing
+    pub fn unbind(&mut self, chord: KeyChord) -> &mut Self {
+        self.bind(chord, KeyBinding::Unbound)
+    }
+
+    /// Remove the binding of a chord. A chord without a binding falls back to a lower priority keymap, or types its character.
+    pub fn remove(&mut self, chord: &KeyChord) -> Option<KeyBinding> {
+        self.bindings.remove(chord)
+    }
+
+    pub fn get(&self, chord: &KeyChord) -> Option<&KeyBinding> {
+        self.bindings.get(chord)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&KeyChord, &KeyBinding)> {
+        self.bindings.iter()
+    }
+}
+
+/// Finds the binding of a chord in `keymaps`, in priority order.
+/// Returns the binding, and true if it is a motion that should extend the selection.
+pub(crate) fn find_binding<'a>(
+    keymaps: &[&'a TextInputKeymap],
+    chord: &KeyChord,
+) -> Option<(&'a KeyBinding, bool)> {
+    if let Some(binding) = keymaps.iter().find_map(|keymap| keymap.get(chord)) {
+        return Some((binding, false));
+    }
+    if !chord.modifiers.shift {
+        return None;
+    }
+    keymaps
+        .iter()
+        .find_map(|keymap| keymap.get(&chord.without_shift()))
+        .filter(|binding| matches!(binding, KeyBinding::Motion(_)))
+        .map(|binding| (binding, true))
+}
+
+/// The action of a binding in an input with the given mode.
+///
+/// Single line inputs submit on Enter, and ignore indents and scrolling.
+/// Password inputs are treated as a single word by word motions and word deletions.
+pub(crate) fn binding_action(
+    binding: &KeyBinding,
+    with_select: bool,
+    mode: &TextInputMode,
+) -> Option<TextInputAction> {
+    let is_multiline = matches!(mode, TextInputMode::MultiLine { .. });
+    let is_password = matches!(mode, TextInputMode::Password { .. });
+    let word_edit = |edit: &TextInputEdit| match edit {
+        TextInputEdit::DeleteWordBackward if is_password => TextInputEdit::DeleteToLineStart,
+        TextInputEdit::DeleteWordForward if is_password => TextInputEdit::DeleteToLineEnd,
+        edit => edit.clone(),
+    };
+    let action = match binding {
+        KeyBinding::Copy => TextInputAction::Copy,
+        KeyBinding::Cut => TextInputAction::Cut,
+        KeyBinding::Paste => TextInputAction::Paste,
+        KeyBinding::Submit => TextInputAction::Submit,
+        KeyBinding::Kill(edit) => TextInputAction::Kill(word_edit(edit)),
+        KeyBinding::Yank => TextInputAction::Yank,
+        KeyBinding::YankPop => TextInputAction::YankPop,
+        KeyBinding::Edit(TextInputEdit::Enter) if !is_multiline => TextInputAction::Submit,
+        KeyBinding::Edit(
+            TextInputEdit::Indent | TextInputEdit::Unindent | TextInputEdit::Scroll { .. },
+        ) if !is_multiline => return None,
+        KeyBinding::Edit(edit) => TextInputAction::Edit(word_edit(edit)),
+        KeyBinding::Motion(motion) => {
+            let motion = match motion {
+                Motion::PreviousWord | Motion::LeftWord if is_password => Motion::Home,
+                Motion::NextWord | Motion::RightWord if is_password => Motion::End,
+                _ => *motion,
+            };
+            TextInputAction::Edit(TextInputEdit::Motion(motion, with_select))
+        }
+        KeyBinding::ToggleOverwrite | KeyBinding::Unbound => return None,
+    };
+    Some(action)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The chord of a key pressed with Alt and Shift
+    fn alt_shift(key: &str) -> KeyChord {
+        let modifiers = KeyModifiers {
+            shift: true,
+            alt: true,
+            ..KeyModifiers::NONE
+        };
+        KeyChord::from_key(&Key::Character(key.into()), modifiers)
+    }
+
+    #[test]
+    fn emacs_buffer_motions_with_shifted_characters_dont_select() {
+        let keymap = TextInputKeymap::emacs();
+        for (key, expected) in [("<", Motion::BufferStart), (">", Motion::BufferEnd)] {
+            let Some((KeyBinding::Motion(motion), with_select)) =
+                find_binding(&[&keymap], &alt_shift(key))
+            else {
+                panic!("M-{key} isn't bound to a motion");
+            };
+            assert_eq!(*motion, expected);
+            assert!(!with_select);
+        }
+    }
+
+    /// The action of a chord in `keymaps`, in an input with the given mode
+    fn action(
+        keymaps: &[&TextInputKeymap],
+        chord: KeyChord,
+        mode: TextInputMode,
+    ) -> Option<TextInputAction> {
+        let (binding, with_select) = find_binding(keymaps, &chord)?;
+        binding_action(binding, with_select, &mode)
+    }
+
+    fn multi_line() -> TextInputMode {
+        TextInputMode::default()
+    }
+
+    #[test]
+    fn windows_linux_preset() {
+        let keymap = TextInputKeymap::windows_linux();
+        let action = |chord| action(&[&keymap], chord, multi_line());
+        assert!(matches!(
+            action(KeyChord::char('c').ctrl()),
+            Some(TextInputAction::Copy)
+        ));
+        assert!(matches!(
+            action(KeyChord::char('z').ctrl()),
+            Some(TextInputAction::Edit(TextInputEdit::Undo))
+        ));
+        assert!(matches!(
+            action(KeyChord::new(Key::ArrowLeft).ctrl()),
+            Some(TextInputAction::Edit(TextInputEdit::Motion(
+                Motion::PreviousWord,
+                false
+            )))
+        ));
+        assert!(matches!(
+            action(KeyChord::new(Key::Backspace).ctrl()),
+            Some(TextInputAction::Edit(TextInputEdit::DeleteWordBackward))
+        ));
+        assert!(matches!(
+            action(KeyChord::new(Key::Delete).ctrl().shift()),
+            Some(TextInputAction::Edit(TextInputEdit::DeleteToLineEnd))
+        ));
+        // Super isn't used for shortcuts
+        assert!(action(KeyChord::char('c').super_key()).is_none());
+    }
+
+    #[test]
+    fn macos_preset() {
+        let keymap = TextInputKeymap::macos();
+        let action = |chord| action(&[&keymap], chord, multi_line());
+        assert!(matches!(
+            action(KeyChord::char('v').super_key()),
+            Some(TextInputAction::Paste)
+        ));
+        assert!(matches!(
+            action(KeyChord::char('z').super_key().shift()),
+            Some(TextInputAction::Edit(TextInputEdit::Redo))
+        ));
+        assert!(matches!(
+            action(KeyChord::new(Key::ArrowLeft).super_key()),
+            Some(TextInputAction::Edit(TextInputEdit::Motion(
+                Motion::Home,
+                false
+            )))
+        ));
+        assert!(matches!(
+            action(KeyChord::new(Key::ArrowRight).alt()),
+            Some(TextInputAction::Edit(TextInputEdit::Motion(
+                Motion::NextWord,
+                false
+            )))
+        ));
+        assert!(matches!(
+            action(KeyChord::new(Key::Backspace).super_key()),
+            Some(TextInputAction::Edit(TextInputEdit::DeleteToLineStart))
+        ));
+        // the Emacs style Ctrl keys of macOS text fields
+        assert!(matches!(
+            action(KeyChord::char('k').ctrl()),
+            Some(TextInputAction::Kill(TextInputEdit::DeleteToLineEnd))
+        ));
+        assert!(action(KeyChord::char('c').ctrl()).is_none());
+    }
+
+    #[test]
+    fn emacs_preset() {
+        let keymap = TextInputKeymap::emacs();
+        let action = |chord| action(&[&keymap], chord, multi_line());
+        assert!(matches!(
+            action(KeyChord::char('a').ctrl()),
+            Some(TextInputAction::Edit(TextInputEdit::Motion(
+                Motion::Home,
+                false
+            )))
+        ));
+        assert!(matches!(
+            action(KeyChord::char('f').alt()),
+            Some(TextInputAction::Edit(TextInputEdit::Motion(
+                Motion::NextWord,
+                false
+            )))
+        ));
+        assert!(matches!(
+            action(KeyChord::char('d').alt()),
+            Some(TextInputAction::Kill(TextInputEdit::DeleteWordForward))
+        ));
+        assert!(matches!(
+            action(KeyChord::char('y').ctrl()),
+            Some(TextInputAction::Yank)
+        ));
+        assert!(matches!(
+            action(KeyChord::char('y').alt()),
+            Some(TextInputAction::YankPop)
+        ));
+        // the Windows and Linux bindings that aren't replaced are kept
+        assert!(matches!(
+            action(KeyChord::char('c').ctrl()),
+            Some(TextInputAction::Copy)
+        ));
+    }
+
+    #[test]
+    fn shifted_motions_extend_the_selection_unless_bound() {
+        let keymap = TextInputKeymap::windows_linux();
+        assert!(matches!(
+            find_binding(&[&keymap], &KeyChord::new(Key::ArrowLeft).shift()),
+            Some((KeyBinding::Motion(Motion::Left), true))
+        ));
+        assert!(matches!(
+            find_binding(&[&keymap], &KeyChord::new(Key::Home).ctrl().shift()),
+            Some((KeyBinding::Motion(Motion::BufferStart), true))
+        ));
+        // bound with Shift
+        assert!(matches!(
+            find_binding(&[&keymap], &KeyChord::new(Key::Tab).shift()),
+            Some((KeyBinding::Edit(TextInputEdit::Unindent), false))
+        ));
+        // only motions fall back to the chord without Shift
+        assert!(find_binding(&[&keymap], &KeyChord::char('c').ctrl().shift()).is_none());
+    }
+
+    #[test]
+    fn password_inputs_treat_the_text_as_one_word() {
+        let keymap = TextInputKeymap::windows_linux();
+        let action = |chord| action(&[&keymap], chord, TextInputMode::password());
+        assert!(matches!(
+            action(KeyChord::new(Key::ArrowLeft).ctrl()),
+            Some(TextInputAction::Edit(TextInputEdit::Motion(
+                Motion::Home,
+                false
+            )))
+        ));
+        assert!(matches!(
+            action(KeyChord::new(Key::ArrowRight).ctrl().shift()),
+            Some(TextInputAction::Edit(TextInputEdit::Motion(
+                Motion::End,
+                true
+            )))
+        ));
+        assert!(matches!(
+            action(KeyChord::new(Key::Backspace).ctrl()),
+            Some(TextInputAction::Edit(TextInputEdit::DeleteToLineStart))
+        ));
+        assert!(matches!(
+            action(KeyChord::new(Key::Delete).ctrl()),
+            Some(TextInputAction::Edit(TextInputEdit::DeleteToLineEnd))
+        ));
+        let keymap = TextInputKeymap::emacs();
+        assert!(matches!(
+            self::action(
+                &[&keymap],
+                KeyChord::char('d').alt(),
+                TextInputMode::password()
+            ),
+            Some(TextInputAction::Kill(TextInputEdit::DeleteToLineEnd))
+        ));
+    }
+
+    #[test]
+    fn single_line_inputs_submit_on_enter_and_ignore_indents() {
+        let keymap = TextInputKeymap::windows_linux();
+        for mode in [TextInputMode::SingleLine, TextInputMode::password()] {
+            let action = |chord| action(&[&keymap], chord, mode);
+            assert!(matches!(
+                action(KeyChord::new(Key::Enter)),
+                Some(TextInputAction::Submit)
+            ));
+            assert!(action(KeyChord::new(Key::Tab)).is_none());
+            assert!(action(KeyChord::new(Key::ArrowUp).ctrl()).is_none());
+        }
+        let action = |chord| action(&[&keymap], chord, multi_line());
+        assert!(matches!(
+            action(KeyChord::new(Key::Enter)),
+            Some(TextInputAction::Edit(TextInputEdit::Enter))
+        ));
+        assert!(matches!(
+            action(KeyChord::new(Key::Enter).shift()),
+            Some(TextInputAction::Submit)
+        ));
+    }
+
+    #[test]
+    fn bind_unbind_and_keymap_priority() {
+        let mut input_keymap = TextInputKeymap::empty();
+        input_keymap
+            .bind(KeyChord::char('c').ctrl(), KeyBinding::Paste)
+            .unbind(KeyChord::char('x').ctrl());
+        let keymap = TextInputKeymap::windows_linux();
+        let keymaps = [&input_keymap, &keymap];
+        let action = |chord| action(&keymaps, chord, multi_line());
+
+        // the input's keymap takes priority
+        assert!(matches!(
+            action(KeyChord::char('c').ctrl()),
+            Some(TextInputAction::Paste)
+        ));
+        assert!(matches!(
+            find_binding(&keymaps, &KeyChord::char('x').ctrl()),
+            Some((KeyBinding::Unbound, false))
+        ));
+        assert!(action(KeyChord::char('x').ctrl()).is_none());
+        assert!(matches!(
+            action(KeyChord::char('v').ctrl()),
+            Some(TextInputAction::Paste)
+        ));
+
+        // removed chords fall back to the lower priority keymap
+        assert!(matches!(
+            input_keymap.remove(&KeyChord::char('c').ctrl()),
+            Some(KeyBinding::Paste)
+        ));
+        let keymaps = [&input_keymap, &keymap];
+        assert!(matches!(
+            self::action(&keymaps, KeyChord::char('c').ctrl(), multi_line()),
+            Some(TextInputAction::Copy)
+        ));
+    }
+
+    #[test]
+    fn chords_with_command_keys_ignore_case() {
+        let modifiers = KeyModifiers {
+            ctrl: true,
+            shift: true,
+            ..KeyModifiers::NONE
+        };
+        let chord = KeyChord::from_key(&Key::Character("C".into()), modifiers);
+        assert_eq!(chord, KeyChord::char('c').ctrl().shift());
+        // typed characters keep their case
+        let chord = KeyChord::from_key(&Key::Character("C".into()), KeyModifiers::NONE);
+        assert_eq!(chord, KeyChord::char('C'));
+    }
+}
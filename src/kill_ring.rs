@@ -0,0 +1,240 @@
+use std::collections::VecDeque;
+use std::ops::Range;
+
+use bevy::ecs::entity::Entity;
+use bevy::ecs::resource::Resource;
+
+/// Default maximum number of entries kept in the [`TextInputKillRing`]
+pub const DEFAULT_KILL_RING_SIZE: usize = 60;
+
+/// The most recent kill or yank, used to decide if the next kill appends and if a yank can be cycled
+#[derive(Clone, Debug)]
+enum LastKillRingAction {
+    /// Text was killed, leaving the cursor at `offset`
+    Kill { entity: Entity, offset: usize },
+    /// The entry at `index` was inserted at `range`
+    Yank {
+        entity: Entity,
+        range: Range<usize>,
+        index: usize,
+    },
+}
+
+/// Emacs style kill ring shared by all text inputs.
+///
+/// `TextInputAction::Kill` deletes text and adds it to the ring. Consecutive kills in the same input are
+/// combined into a single entry. `TextInputAction::Yank` inserts the most recent entry, and a following
+/// `TextInputAction::YankPop` replaces the inserted text with the entry before it.
+/// The kill ring is separate from the system clipboard.
+#[derive(Resource, Debug)]
+pub struct TextInputKillRing {
+    /// Killed text, most recent first
+    entries: VecDeque<String>,
+    /// Maximum number of entries kept, the oldest entries are discarded first
+    pub max_entries: usize,
+    last_action: Option<LastKillRingAction>,
+}
+
+impl Default for TextInputKillRing {
+    fn default() -> Self {
+        Self {
+            entries: VecDeque::new(),
+            max_entries: DEFAULT_KILL_RING_SIZE,
+            last_action: None,
+        }
+    }
+}
+
+impl TextInputKillRing {
+    /// The killed text, most recent first
+    pub fn entries(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().map(String::as_str)
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.last_action = None;
+    }
+
+    /// Record text killed from an input.
+    /// `offset` is the byte offset of the cursor before the kill, `killed_at` the offset the text was removed from.
+    /// Text killed where the previous kill in the same input left the cursor is combined with the previous entry.
+    pub(crate) fn kill(&mut self, entity: Entity, offset: usize, killed_at: usize, text: String) {
+        if text.is_empty() {
+            return;
+        }
+        let append = matches!(
+            self.last_action,
+            Some(LastKillRingAction::Kill { entity: last_entity, offset: last_offset })
+                if last_entity == entity && last_offset == offset
+        );
+        match self.entries.front_mut() {
+            Some(entry) if append => {
+                if killed_at < offset {
+                    // killed backwards, the text goes before the previous kill
+                    entry.insert_str(0, &text);
+                } else {
+                    entry.push_str(&text);
+                }
+            }
+            _ => {
+                self.entries.push_front(text);
+                self.entries.truncate(self.max_entries.max(1));
+            }
+        }
+        self.last_action = Some(LastKillRingAction::Kill {
+            entity,
+            offset: killed_at,
+        });
+    }
+
+    /// The most recent entry, to be inserted by a yank
+    pub(crate) fn yank(&self) -> Option<&str> {
+        self.entries.front().map(String::as_str)
+    }
+
+    /// Record an entry inserted at `range` by a yank
+    pub(crate) fn yanked(&mut self, entity: Entity, range: Range<usize>, index: usize) {
+        self.last_action = Some(LastKillRingAction::Yank {
+            entity,
+            range,
+            index,
+        });
+    }
+
+    /// If the last action was a yank in this input and the cursor is still at the end of the inserted text,
+    /// returns the range of the inserted text and the index and text of the entry to replace it with
+    pub(crate) fn yank_pop(
+        &self,
+        entity: Entity,
+        offset: usize,
+    ) -> Option<(Range<usize>, usize, &str)> {
+        match &self.last_action {
+            Some(LastKillRingAction::Yank {
+                entity: last_entity,
+                range,
+                index,
+            }) if *last_entity == entity && range.end == offset => {
+                let index = (index + 1) % self.entries.len().max(1);
+                let entry = self.entries.get(index)?;
+                Some((range.clone(), index, entry.as_str()))
+            }
+            _ => None,
+        }
+    }
+
+    /// Ends a sequence of kills or yanks in an input, the next kill starts a new entry
+    pub(crate) fn interrupt(&mut self, entity: Entity) {
+        let last_entity = match &self.last_action {
+            Some(
+                LastKillRingAction::Kill { entity, .. } | LastKillRingAction::Yank { entity, .. },
+            ) => *entity,
+            None => return,
+        };
+        if last_entity == entity {
+            self.last_action = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: Entity = Entity::from_raw(1);
+    const OTHER_INPUT: Entity = Entity::from_raw(2);
+
+    fn entries(kill_ring: &TextInputKillRing) -> Vec<&str> {
+        kill_ring.entries().collect()
+    }
+
+    #[test]
+    fn consecutive_kills_are_combined() {
+        let mut kill_ring = TextInputKillRing::default();
+        // forward kills leave the cursor in place and are appended
+        kill_ring.kill(INPUT, 5, 5, "abc".to_string());
+        kill_ring.kill(INPUT, 5, 5, "def".to_string());
+        assert_eq!(entries(&kill_ring), ["abcdef"]);
+
+        // backward kills move the cursor back and are prepended
+        let mut kill_ring = TextInputKillRing::default();
+        kill_ring.kill(INPUT, 10, 7, "xyz".to_string());
+        kill_ring.kill(INPUT, 7, 4, "uvw".to_string());
+        assert_eq!(entries(&kill_ring), ["uvwxyz"]);
+    }
+
+    #[test]
+    fn kills_elsewhere_start_new_entries() {
+        let mut kill_ring = TextInputKillRing::default();
+        kill_ring.kill(INPUT, 0, 0, "a".to_string());
+        // a different position
+        kill_ring.kill(INPUT, 3, 3, "b".to_string());
+        // a different input
+        kill_ring.kill(OTHER_INPUT, 3, 3, "c".to_string());
+        // empty kills are ignored
+        kill_ring.kill(OTHER_INPUT, 3, 3, String::new());
+        kill_ring.kill(OTHER_INPUT, 3, 3, "d".to_string());
+        assert_eq!(entries(&kill_ring), ["cd", "b", "a"]);
+    }
+
+    #[test]
+    fn interrupt_ends_the_kill_sequence_of_its_input() {
+        let mut kill_ring = TextInputKillRing::default();
+        kill_ring.kill(INPUT, 0, 0, "a".to_string());
+        kill_ring.interrupt(OTHER_INPUT);
+        kill_ring.kill(INPUT, 0, 0, "b".to_string());
+        assert_eq!(entries(&kill_ring), ["ab"]);
+
+        kill_ring.interrupt(INPUT);
+        kill_ring.kill(INPUT, 0, 0, "c".to_string());
+        assert_eq!(entries(&kill_ring), ["c", "ab"]);
+    }
+
+    #[test]
+    fn oldest_entries_are_discarded() {
+        let mut kill_ring = TextInputKillRing {
+            max_entries: 2,
+            ..Default::default()
+        };
+        for (offset, text) in ["a", "b", "c"].into_iter().enumerate() {
+            kill_ring.kill(INPUT, offset * 10, offset * 10, text.to_string());
+        }
+        assert_eq!(entries(&kill_ring), ["c", "b"]);
+    }
+
+    #[test]
+    fn yank_pop_cycles_through_the_entries() {
+        let mut kill_ring = TextInputKillRing::default();
+        for (offset, text) in ["a", "b", "c"].into_iter().enumerate() {
+            kill_ring.kill(INPUT, offset * 10, offset * 10, text.to_string());
+        }
+        assert_eq!(kill_ring.yank(), Some("c"));
+        kill_ring.yanked(INPUT, 4..5, 0);
+
+        let mut popped = vec![];
+        for _ in 0..3 {
+            let (range, index, text) = kill_ring.yank_pop(INPUT, 5).unwrap();
+            assert_eq!(range, 4..5);
+            popped.push(text.to_string());
+            kill_ring.yanked(INPUT, range, index);
+        }
+        assert_eq!(popped, ["b", "a", "c"]);
+    }
+
+    #[test]
+    fn yank_pop_needs_a_preceding_yank_at_the_cursor() {
+        let mut kill_ring = TextInputKillRing::default();
+        kill_ring.kill(INPUT, 0, 0, "a".to_string());
+        kill_ring.kill(INPUT, 10, 10, "b".to_string());
+        assert!(kill_ring.yank_pop(INPUT, 10).is_none());
+
+        kill_ring.yanked(INPUT, 0..1, 0);
+        // the cursor moved, or the pop is in another input
+        assert!(kill_ring.yank_pop(INPUT, 0).is_none());
+        assert!(kill_ring.yank_pop(OTHER_INPUT, 1).is_none());
+        assert!(kill_ring.yank_pop(INPUT, 1).is_some());
+
+        kill_ring.interrupt(INPUT);
+        assert!(kill_ring.yank_pop(INPUT, 1).is_none());
+    }
+}
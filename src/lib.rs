@@ -1,12 +1,23 @@
 pub mod actions;
+pub mod carets;
 pub mod clipboard;
 pub mod edit;
+pub mod gutter;
+pub mod highlight;
+pub mod history;
+pub mod keymap;
+pub mod kill_ring;
 pub mod render;
+pub mod scrollbar;
+pub mod search;
+pub mod spans;
 pub mod text_input_pipeline;
 
 use std::collections::VecDeque;
+use std::ops::Range;
+use std::sync::Arc;
 
-use actions::TextInputAction;
+use actions::{TextInputAction, TextInputEdit};
 use bevy::app::{Plugin, PostUpdate};
 use bevy::asset::AssetEvents;
 use bevy::color::Color;
@@ -26,18 +37,32 @@ use bevy::math::{Rect, Vec2};
 use bevy::prelude::ReflectComponent;
 use bevy::reflect::{Reflect, std_traits::ReflectDefault};
 use bevy::render::{ExtractSchedule, RenderApp};
-use bevy::text::cosmic_text::{Buffer, Edit, Editor, Metrics, Wrap};
+use bevy::text::cosmic_text::{
+    Buffer, Change, Cursor, Edit, Editor, Metrics, Scroll, Selection, Wrap,
+};
 use bevy::text::{GlyphAtlasInfo, TextFont};
 use bevy::text::{JustifyText, TextColor};
 use bevy::ui::{Node, RenderUiSystem, UiSystem, extract_text_sections};
+use carets::Caret;
 use edit::{
-    cursor_blink_system, listen_ime_events, mouse_wheel_scroll, on_drag_text_input,
-    on_focused_keyboard_input, on_move_clear_multi_click, on_multi_click_set_selection,
-    on_text_input_pressed, process_text_input_queues, toggle_ime_on_focus,
+    DragScrollSettings, MultiClickData, MultiClickSettings, cursor_blink_system, drag_auto_scroll,
+    listen_ime_events, mouse_wheel_scroll, on_drag_end_text_input, on_drag_text_input,
+    on_focused_keyboard_input, on_text_input_pressed, process_text_input_queues, send_focus_events,
+    send_selection_changed_events, toggle_ime_on_focus, unfocus_on_click_outside,
+    update_ime_position,
 };
+use highlight::highlight_text_inputs;
+use history::TextInputHistory;
+use keymap::{KeyModifiers, TextInputKeymap};
 use once_cell::sync::Lazy;
 use regex::Regex;
-use render::{extract_text_input_nodes, extract_text_input_prompts};
+use render::{
+    extract_text_input_gutters, extract_text_input_nodes, extract_text_input_prompts,
+    extract_text_input_scrollbars,
+};
+use scrollbar::update_text_input_scrollbars;
+use search::{SearchOptions, TextInputSearch, update_text_input_searches};
+use spans::{SpanStyles, TextInputSpanStyle};
 use text_input_pipeline::{
     TextInputPipeline, remove_dropped_font_atlas_sets_from_text_input_pipeline,
     text_input_prompt_system, text_input_system,
@@ -48,8 +73,16 @@ pub struct TextInputPlugin;
 impl Plugin for TextInputPlugin {
     fn build(&self, app: &mut bevy::app::App) {
         app.add_event::<TextSubmitEvent>()
+            .add_event::<TextChangedEvent>()
+            .add_event::<TextInputFocused>()
+            .add_event::<TextInputBlurred>()
+            .add_event::<TextInputSelectionChanged>()
             .add_plugins(bevy::input_focus::InputDispatchPlugin)
             .init_resource::<TextInputGlobalState>()
+            .init_resource::<TextInputKeymap>()
+            .init_resource::<kill_ring::TextInputKillRing>()
+            .init_resource::<MultiClickSettings>()
+            .init_resource::<DragScrollSettings>()
             .init_resource::<TextInputPipeline>()
             .init_resource::<clipboard::Clipboard>()
             .add_systems(
@@ -61,9 +94,17 @@ impl Plugin for TextInputPlugin {
                         listen_ime_events,
                         cursor_blink_system,
                         mouse_wheel_scroll,
+                        drag_auto_scroll,
                         process_text_input_queues,
+                        unfocus_on_click_outside,
+                        send_focus_events,
+                        send_selection_changed_events,
+                        update_text_input_searches,
                         update_text_input_contents,
+                        highlight_text_inputs,
                         text_input_system,
+                        update_text_input_scrollbars,
+                        update_ime_position,
                         text_input_prompt_system,
                     )
                         .chain()
@@ -77,7 +118,12 @@ impl Plugin for TextInputPlugin {
 
         render_app.add_systems(
             ExtractSchedule,
-            (extract_text_input_prompts, extract_text_input_nodes)
+            (
+                extract_text_input_prompts,
+                extract_text_input_gutters,
+                extract_text_input_nodes,
+                extract_text_input_scrollbars,
+            )
                 .chain()
                 .in_set(RenderUiSystem::ExtractText)
                 .after(extract_text_sections),
@@ -93,7 +139,9 @@ impl Plugin for TextInputPlugin {
     TextInputLayoutInfo,
     TextInputStyle,
     TextColor,
-    TextInputQueue
+    TextInputQueue,
+    TextInputHistory,
+    MultiClickData
 )]
 #[component(
     on_add = on_add_textinputnode,
@@ -113,12 +161,22 @@ pub struct TextInputNode {
     pub allow_overwrite_mode: bool,
     /// Can the text input be activated
     pub is_enabled: bool,
+    /// The text can be focused, selected, copied and scrolled but not edited.
+    /// Edits that set the text programmatically, such as `TextInputEdit::SetText`, are still applied.
+    pub is_read_only: bool,
     /// Activate on pointer down
     pub focus_on_pointer_down: bool,
     /// Deactivate after text submitted
     pub unfocus_on_submit: bool,
     /// Text justification
     pub justification: JustifyText,
+    /// Tab and Shift+Tab move the focus to the next and previous entity with a `TabIndex`, and Ctrl+Tab types a tab.
+    /// The input needs a `TabIndex` and an ancestor with a `TabGroup`.
+    pub tab_navigation: bool,
+    /// Select all of the text when the input gains focus
+    pub select_all_on_focus: bool,
+    /// Lose focus and clear the selection when the primary pointer button is pressed outside of the input
+    pub unfocus_on_click_outside: bool,
 }
 
 impl Default for TextInputNode {
@@ -130,9 +188,13 @@ impl Default for TextInputNode {
             max_chars: None,
             allow_overwrite_mode: true,
             is_enabled: true,
+            is_read_only: false,
             focus_on_pointer_down: true,
             unfocus_on_submit: true,
             justification: JustifyText::Left,
+            tab_navigation: false,
+            select_all_on_focus: false,
+            unfocus_on_click_outside: false,
         }
     }
 }
@@ -140,9 +202,8 @@ impl Default for TextInputNode {
 fn on_add_textinputnode(mut world: DeferredWorld, context: HookContext) {
     for mut observer in [
         Observer::new(on_drag_text_input),
+        Observer::new(on_drag_end_text_input),
         Observer::new(on_text_input_pressed),
-        Observer::new(on_multi_click_set_selection),
-        Observer::new(on_move_clear_multi_click),
         Observer::new(on_focused_keyboard_input),
     ] {
         observer.watch_entity(context.entity);
@@ -166,6 +227,105 @@ pub struct TextSubmitEvent {
     pub text: String,
 }
 
+/// Sent and triggered on the text input entity when its text is changed by an action from its `TextInputQueue`.
+/// Each action that changes the text produces a separate event.
+#[derive(Event, Clone, Debug)]
+pub struct TextChangedEvent {
+    /// The text input entity whose text changed
+    pub entity: Entity,
+    /// The text before the change
+    pub old_text: String,
+    /// The text after the change
+    pub new_text: String,
+    /// The part of `old_text` that was replaced
+    pub delta: TextDelta,
+}
+
+/// Sent and triggered on a text input entity when it gains focus
+#[derive(Event, Clone, Debug)]
+pub struct TextInputFocused {
+    pub entity: Entity,
+}
+
+/// Sent and triggered on a text input entity when it loses focus
+#[derive(Event, Clone, Debug)]
+pub struct TextInputBlurred {
+    pub entity: Entity,
+}
+
+/// Sent and triggered on a text input entity when its cursor or one of its carets moves, or a selection changes.
+/// Without a selection both ranges are empty, at the position of the cursor.
+#[derive(Event, Clone, Debug)]
+pub struct TextInputSelectionChanged {
+    pub entity: Entity,
+    /// The selected byte range of the text
+    pub bytes: Range<usize>,
+    /// The selected range of the text in chars
+    pub chars: Range<usize>,
+    /// The selected byte ranges of the input's carets, in the order of `TextInputBuffer::carets`.
+    /// The range of a caret without a selection is empty, at the position of the caret.
+    pub carets: Vec<Range<usize>>,
+}
+
+/// A replacement of a range of text
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TextDelta {
+    /// Byte range of the replaced text in the old text
+    pub range: Range<usize>,
+    /// The text inserted in place of `range`
+    pub text: String,
+}
+
+impl TextDelta {
+    /// The replacement covering every item of `change`, found by reverting the change from `new_text`,
+    /// the text after the change was applied. Returns the text before the change along with the delta.
+    pub(crate) fn from_change(change: &Change, new_text: &str) -> (String, Self) {
+        // Byte offset of `cursor` in `text`, with its lines separated by `\n` like `get_text`
+        fn offset(text: &str, cursor: Cursor) -> usize {
+            let line_start = text
+                .split('\n')
+                .take(cursor.line)
+                .map(|line| line.len() + 1)
+                .sum::<usize>();
+            (line_start + cursor.index).min(text.len())
+        }
+
+        let mut text = new_text.to_string();
+        // The changed range of `text` and the corresponding range of `new_text`
+        let mut changed: Option<(Range<usize>, Range<usize>)> = None;
+        for item in change.items.iter().rev() {
+            let start = offset(&text, item.start);
+            let end = if item.insert {
+                offset(&text, item.end)
+            } else {
+                start
+            };
+            let (range, new_range) = changed.get_or_insert((start..start, start..start));
+            if start < range.start {
+                new_range.start -= range.start - start;
+                range.start = start;
+            }
+            if range.end < end {
+                new_range.end += end - range.end;
+                range.end = end;
+            }
+            if item.insert {
+                text.replace_range(start..end, "");
+                range.end -= end - start;
+            } else {
+                text.insert_str(start, &item.text);
+                range.end += item.text.len();
+            }
+        }
+        let (range, new_range) = changed.unwrap_or_default();
+        let delta = Self {
+            range,
+            text: new_text[new_range].to_string(),
+        };
+        (text, delta)
+    }
+}
+
 /// Mode of text input
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub enum TextInputMode {
@@ -176,10 +336,17 @@ pub enum TextInputMode {
     /// Scrolls horizontally
     /// Submit on enter
     SingleLine,
+    /// Single line text input that displays every character as `mask`
+    /// Copy and cut are disabled and word motions treat the text as a single word
+    /// Submit on enter
+    Password { mask: char },
 }
 
-/// Filter for text input
-#[derive(Copy, Clone, Debug, PartialEq)]
+/// Filter for text input.
+///
+/// Filters are checked against the whole of the text that would result from an edit,
+/// so they need to accept partially entered values too.
+#[derive(Clone)]
 pub enum TextInputFilter {
     /// Integer input
     /// accepts only digits and a leading sign
@@ -190,21 +357,78 @@ pub enum TextInputFilter {
     /// Hexadecimal input
     /// accepts only `0-9`, `a-f` and `A-F`
     Hex,
+    /// Alphanumeric input
+    /// accepts only letters and digits
+    Alphanumeric,
+    /// Identifier input
+    /// accepts a letter or underscore followed by letters, digits and underscores
+    Identifier,
+    /// Email address input
+    /// accepts text without whitespace containing at most one `@`
+    Email,
+    /// ASCII input
+    /// accepts only ASCII characters
+    Ascii,
+    /// Accepts text matched by the regex.
+    /// The regex should be anchored with `^` and `$` to match the whole text.
+    Regex(Regex),
+    /// Accepts text for which the predicate returns true
+    Custom(Arc<dyn Fn(&str) -> bool + Send + Sync>),
+}
+
+impl core::fmt::Debug for TextInputFilter {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Integer => write!(f, "Integer"),
+            Self::Decimal => write!(f, "Decimal"),
+            Self::Hex => write!(f, "Hex"),
+            Self::Alphanumeric => write!(f, "Alphanumeric"),
+            Self::Identifier => write!(f, "Identifier"),
+            Self::Email => write!(f, "Email"),
+            Self::Ascii => write!(f, "Ascii"),
+            Self::Regex(regex) => f.debug_tuple("Regex").field(regex).finish(),
+            Self::Custom(_) => f.debug_tuple("Custom").finish_non_exhaustive(),
+        }
+    }
 }
 
 static INTEGER_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"^-?$|^-?\d+$").unwrap());
 static DECIMAL_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"^-?$|^-?\d*\.?\d*$").unwrap());
+static HEX_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[0-9a-fA-F]*$").unwrap());
+static ALPHANUMERIC_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^[\p{Alphabetic}\p{Nd}]*$").unwrap());
+static IDENTIFIER_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^$|^[\p{Alphabetic}_][\p{Alphabetic}\p{Nd}_]*$").unwrap());
+static EMAIL_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[^\s@]*(@[^\s@]*)?$").unwrap());
+static ASCII_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[\x00-\x7F]*$").unwrap());
 
 impl TextInputFilter {
+    /// Filter that accepts text for which `predicate` returns true
+    pub fn custom(predicate: impl Fn(&str) -> bool + Send + Sync + 'static) -> Self {
+        Self::Custom(Arc::new(predicate))
+    }
+
     pub fn regex(&self) -> Option<&regex::Regex> {
         match self {
             TextInputFilter::Integer => Some(&INTEGER_REGEX),
             TextInputFilter::Decimal => Some(&DECIMAL_REGEX),
-            TextInputFilter::Hex => None,
+            TextInputFilter::Hex => Some(&HEX_REGEX),
+            TextInputFilter::Alphanumeric => Some(&ALPHANUMERIC_REGEX),
+            TextInputFilter::Identifier => Some(&IDENTIFIER_REGEX),
+            TextInputFilter::Email => Some(&EMAIL_REGEX),
+            TextInputFilter::Ascii => Some(&ASCII_REGEX),
+            TextInputFilter::Regex(regex) => Some(regex),
+            TextInputFilter::Custom(_) => None,
         }
     }
 
-
+    /// Returns true if `text` is accepted by the filter
+    pub fn is_match(&self, text: &str) -> bool {
+        match self {
+            TextInputFilter::Custom(predicate) => predicate(text),
+            _ => self.regex().is_none_or(|regex| regex.is_match(text)),
+        }
+    }
 }
 
 impl Default for TextInputMode {
@@ -216,6 +440,19 @@ impl Default for TextInputMode {
 }
 
 impl TextInputMode {
+    /// Password input masked with `•`
+    pub const fn password() -> Self {
+        Self::Password { mask: '•' }
+    }
+
+    /// Returns the mask character if this is a password input
+    pub fn mask(&self) -> Option<char> {
+        match self {
+            TextInputMode::Password { mask } => Some(*mask),
+            _ => None,
+        }
+    }
+
     pub fn wrap(&self) -> Wrap {
         match self {
             TextInputMode::MultiLine { wrap } => *wrap,
@@ -231,11 +468,221 @@ pub struct TextInputBuffer {
     pub(crate) cursor_blink_time: f32,
     pub(crate) needs_update: bool,
     pub(crate) prompt_buffer: Option<Buffer>,
+    pub(crate) display_text: Option<DisplayText>,
+    pub(crate) preedit: Option<ImePreedit>,
+    pub(crate) preedit_rects: Vec<Rect>,
+    pub(crate) span_styles: SpanStyles,
+    /// Underlines and strikethroughs, with the span index of their text
+    pub(crate) decoration_rects: Vec<(Rect, usize)>,
+    pub(crate) search: Option<TextInputSearch>,
+    pub(crate) search_rects: Vec<Rect>,
+    pub(crate) carets: Vec<Caret>,
+    /// Position where an Alt+drag box selection started, in the coordinates of the buffer's layout
+    pub(crate) box_anchor: Option<Vec2>,
+    /// Position of the pointer while drag selecting, relative to the top left of the text area in physical pixels
+    pub(crate) drag_position: Option<Vec2>,
+    /// Cursor, selection and carets when `TextInputSelectionChanged` was last sent
+    pub(crate) last_selection: (Cursor, Selection, Vec<(Cursor, Option<Cursor>)>),
+    /// Incremented each time an action changes the text.
+    /// The buffer is mutated every frame by the cursor blink, so change detection can't be used to find text changes.
+    pub(crate) text_generation: u64,
 }
+
 impl TextInputBuffer {
     pub fn get_text(&self) -> String {
         self.editor.with_buffer(get_text)
     }
+
+    /// The styled ranges of the text, as byte ranges into the text returned by `get_text`.
+    /// Unstyled text isn't included.
+    pub fn spans(&self) -> Vec<(Range<usize>, TextInputSpanStyle)> {
+        self.editor
+            .with_buffer(|buffer| self.span_styles.spans(buffer))
+    }
+
+    /// The style of a `TextInputGlyph` with the given `span_index`, `None` for unstyled text
+    pub fn span_style(&self, span_index: usize) -> Option<&TextInputSpanStyle> {
+        self.span_styles.get(span_index)
+    }
+
+    /// The active search, if any. Searches are started with `TextInputAction::Find`.
+    pub fn search(&self) -> Option<&TextInputSearch> {
+        self.search.as_ref()
+    }
+
+    /// The additional carets of the input, not including the editor's own cursor.
+    /// Typing, deleting and pasting apply at the editor's cursor and every caret.
+    pub fn carets(&self) -> &[Caret] {
+        &self.carets
+    }
+
+    /// The selections of the editor and every caret, as the start and end of each selection
+    pub fn selections(&self) -> Vec<(Cursor, Cursor)> {
+        self.editor
+            .selection_bounds()
+            .into_iter()
+            .chain(self.carets.iter().filter_map(Caret::selection_bounds))
+            .collect()
+    }
+
+    /// The uncommitted IME composition text, if any
+    pub fn preedit(&self) -> Option<&ImePreedit> {
+        self.preedit.as_ref()
+    }
+
+    /// Set the uncommitted IME composition text
+    pub(crate) fn set_preedit(&mut self, preedit: Option<ImePreedit>) {
+        if self.preedit != preedit {
+            self.preedit = preedit;
+            self.editor.set_redraw(true);
+        }
+    }
+
+    /// Scroll of the displayed text
+    pub(crate) fn display_scroll(&self) -> Scroll {
+        match &self.display_text {
+            Some(display_text) => display_text.buffer.scroll(),
+            None => self.editor.with_buffer(|buffer| buffer.scroll()),
+        }
+    }
+
+    /// Position of the cursor in the displayed text
+    pub(crate) fn display_cursor_position(&self) -> Option<(i32, i32)> {
+        self.display_position(self.editor.cursor())
+    }
+
+    /// Position of a cursor into the buffer in the displayed text
+    pub(crate) fn display_position(&self, cursor: Cursor) -> Option<(i32, i32)> {
+        match &self.display_text {
+            Some(display_text) => {
+                let cursor = self
+                    .editor
+                    .with_buffer(|buffer| display_text.display_caret(buffer, cursor))?;
+                display_text.cursor_position(cursor)
+            }
+            None => self
+                .editor
+                .with_buffer(|buffer| text_input_pipeline::cursor_position(buffer, cursor)),
+        }
+    }
+}
+
+/// Uncommitted text from an IME composition
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ImePreedit {
+    /// The composition text
+    pub text: String,
+    /// Byte range of the cursor within the composition text, if the cursor should be shown
+    pub cursor: Option<(usize, usize)>,
+}
+
+/// What a `DisplayText` shows in place of the buffer's text
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum DisplayTextKind {
+    /// Each character in the buffer is replaced by one mask character
+    Masked { mask: char },
+    /// The buffer's text with an IME preedit string inserted at `position`
+    Preedit {
+        preedit: ImePreedit,
+        position: Cursor,
+    },
+}
+
+/// Text displayed in place of the buffer's contents, used by password inputs and during IME composition.
+#[derive(Debug)]
+pub(crate) struct DisplayText {
+    pub kind: DisplayTextKind,
+    pub buffer: Buffer,
+}
+
+impl DisplayText {
+    /// The displayed text for the given buffer
+    pub fn text(&self, buffer: &Buffer) -> String {
+        match &self.kind {
+            DisplayTextKind::Masked { mask } => {
+                let mask = mask.to_string();
+                buffer
+                    .lines
+                    .iter()
+                    .map(|line| mask.repeat(line.text().chars().count()))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            }
+            DisplayTextKind::Preedit { preedit, position } => {
+                let mut lines = buffer
+                    .lines
+                    .iter()
+                    .map(|line| line.text().to_string())
+                    .collect::<Vec<_>>();
+                if let Some(line) = lines.get_mut(position.line) {
+                    line.insert_str(position.index.min(line.len()), &preedit.text);
+                }
+                lines.join("\n")
+            }
+        }
+    }
+
+    /// Converts a cursor in the buffer's text to the corresponding cursor in the displayed text
+    pub fn display_cursor(&self, buffer: &Buffer, cursor: Cursor) -> Cursor {
+        let index = match &self.kind {
+            DisplayTextKind::Masked { mask } => {
+                buffer.lines.get(cursor.line).map_or(0, |line| {
+                    line.text()[..cursor.index.min(line.text().len())]
+                        .chars()
+                        .count()
+                }) * mask.len_utf8()
+            }
+            DisplayTextKind::Preedit { preedit, position } => {
+                if cursor.line == position.line && position.index < cursor.index {
+                    cursor.index + preedit.text.len()
+                } else {
+                    cursor.index
+                }
+            }
+        };
+        Cursor::new_with_affinity(cursor.line, index, cursor.affinity)
+    }
+
+    /// Position of the text cursor in the displayed text.
+    /// During IME composition the cursor is drawn inside the preedit text, and is hidden if the IME requests it.
+    pub fn display_caret(&self, buffer: &Buffer, cursor: Cursor) -> Option<Cursor> {
+        match &self.kind {
+            DisplayTextKind::Preedit { preedit, position } => preedit
+                .cursor
+                .map(|(_, end)| Cursor::new(position.line, position.index + end)),
+            DisplayTextKind::Masked { .. } => Some(self.display_cursor(buffer, cursor)),
+        }
+    }
+
+    /// Converts a cursor in the displayed text to the corresponding cursor in the buffer's text
+    pub fn buffer_cursor(&self, buffer: &Buffer, cursor: Cursor) -> Cursor {
+        let index = match &self.kind {
+            DisplayTextKind::Masked { mask } => {
+                let char_index = cursor.index / mask.len_utf8();
+                buffer.lines.get(cursor.line).map_or(0, |line| {
+                    line.text()
+                        .char_indices()
+                        .nth(char_index)
+                        .map_or(line.text().len(), |(index, _)| index)
+                })
+            }
+            DisplayTextKind::Preedit { preedit, position } => {
+                if cursor.line != position.line || cursor.index < position.index {
+                    cursor.index
+                } else if cursor.index < position.index + preedit.text.len() {
+                    position.index
+                } else {
+                    cursor.index - preedit.text.len()
+                }
+            }
+        };
+        Cursor::new_with_affinity(cursor.line, index, cursor.affinity)
+    }
+
+    /// Position of a cursor in the displayed text
+    pub fn cursor_position(&self, cursor: Cursor) -> Option<(i32, i32)> {
+        text_input_pipeline::cursor_position(&self.buffer, cursor)
+    }
 }
 
 impl Default for TextInputBuffer {
@@ -246,6 +693,18 @@ impl Default for TextInputBuffer {
             cursor_blink_time: 0.,
             needs_update: true,
             prompt_buffer: None,
+            display_text: None,
+            preedit: None,
+            preedit_rects: vec![],
+            span_styles: SpanStyles::default(),
+            decoration_rects: vec![],
+            search: None,
+            search_rects: vec![],
+            carets: vec![],
+            box_anchor: None,
+            drag_position: None,
+            last_selection: (Cursor::default(), Selection::None, vec![]),
+            text_generation: 0,
         }
     }
 }
@@ -293,8 +752,12 @@ pub struct TextInputStyle {
     pub cursor_color: Color,
     /// Selection color
     pub selection_color: Color,
+    /// Color of the highlights behind the matches of a search
+    pub search_match_color: Color,
     /// Selected text tint, if unset uses the `TextColor`
     pub selected_text_color: Option<Color>,
+    /// Color of the underline beneath IME composition text, if unset uses the `TextColor`
+    pub preedit_underline_color: Option<Color>,
     /// Width of the cursor
     pub cursor_width: f32,
     /// Corner radius in logical pixels
@@ -310,7 +773,9 @@ impl Default for TextInputStyle {
         Self {
             cursor_color: GRAY_400.into(),
             selection_color: SKY_BLUE.into(),
+            search_match_color: Color::srgba(1., 0.8, 0., 0.4),
             selected_text_color: None,
+            preedit_underline_color: None,
             cursor_width: 3.,
             cursor_radius: 0.,
             cursor_height: 1.,
@@ -324,13 +789,8 @@ fn get_text(buffer: &Buffer) -> String {
         .lines
         .iter()
         .map(|buffer_line| buffer_line.text())
-        .fold(String::new(), |mut out, line| {
-            if !out.is_empty() {
-                out.push('\n');
-            }
-            out.push_str(line);
-            out
-        })
+        .collect::<Vec<_>>()
+        .join("\n")
 }
 
 #[derive(Component, Clone, Default, Debug, Reflect)]
@@ -358,6 +818,8 @@ pub struct TextInputGlyph {
     pub byte_length: usize,
 }
 
+/// Copy of the text in a `TextInputBuffer`, updated after edits.
+/// Optional component. To change the text, queue an edit with `TextInputQueue::set_text`.
 #[derive(Default, Debug, Component, PartialEq)]
 pub struct TextInputContents {
     text: String,
@@ -382,10 +844,8 @@ pub fn update_text_input_contents(
 
 #[derive(Resource, Default)]
 pub struct TextInputGlobalState {
-    /// Shift is held down
-    pub shift: bool,
-    /// Ctrl or Command key is held down
-    pub command: bool,
+    /// The modifier keys held down. With Alt or Option held down, clicking adds carets and dragging selects a rectangle of text.
+    pub modifiers: KeyModifiers,
     /// If true typed glyphs overwrite the glyph at the current cursor position, instead of inserting before it.
     pub overwrite_mode: bool,
 }
@@ -408,12 +868,301 @@ impl TextInputQueue {
     }
 
     /// Get the next action
+    #[allow(clippy::should_implement_trait)]
     pub fn next(&mut self) -> Option<TextInputAction> {
         self.actions.pop_front()
     }
 
+    /// Queue an edit replacing all the text in the buffer.
+    /// The edit is rejected if the text would exceed `max_chars` or doesn't match the input's filter, see `TextInputEdit::SetText`.
+    pub fn set_text(&mut self, text: impl Into<String>) {
+        self.add(TextInputAction::Edit(TextInputEdit::SetText(text.into())));
+    }
+
+    /// Queue an edit inserting text at a byte offset into the buffer's text.
+    /// The edit is rejected if the offset isn't on a char boundary or the text would exceed `max_chars`, see `TextInputEdit::SetText`.
+    pub fn insert_at(&mut self, index: usize, text: impl Into<String>) {
+        self.add(TextInputAction::Edit(TextInputEdit::InsertAt {
+            index,
+            text: text.into(),
+        }));
+    }
+
+    /// Queue an edit replacing a byte range of the buffer's text.
+    /// The edit is rejected if the range doesn't lie on char boundaries or the text would exceed `max_chars`, see `TextInputEdit::SetText`.
+    pub fn replace_range(&mut self, range: Range<usize>, text: impl Into<String>) {
+        self.add(TextInputAction::Edit(TextInputEdit::ReplaceRange {
+            range,
+            text: text.into(),
+        }));
+    }
+
+    /// Queue setting the style of a byte range of the buffer's text
+    pub fn set_style(&mut self, range: Range<usize>, style: TextInputSpanStyle) {
+        self.add(TextInputAction::SetStyle {
+            range,
+            style: Some(style),
+        });
+    }
+
+    /// Queue removing the styling from a byte range of the buffer's text
+    pub fn clear_style(&mut self, range: Range<usize>) {
+        self.add(TextInputAction::SetStyle { range, style: None });
+    }
+
+    /// Queue adding a caret at `cursor`, or removing the caret already there
+    pub fn add_caret(&mut self, cursor: Cursor) {
+        self.add(TextInputAction::Edit(TextInputEdit::AddCaret(cursor)));
+    }
+
+    /// Queue a search for `query`, highlighting its matches
+    pub fn find(&mut self, query: impl Into<String>, options: SearchOptions) {
+        self.add(TextInputAction::Find {
+            query: query.into(),
+            options,
+        });
+    }
+
+    /// Queue selecting the next match of the search
+    pub fn find_next(&mut self) {
+        self.add(TextInputAction::FindNext);
+    }
+
+    /// Queue selecting the previous match of the search
+    pub fn find_previous(&mut self) {
+        self.add(TextInputAction::FindPrevious);
+    }
+
+    /// Queue replacing the selected match of the search and selecting the next match
+    pub fn replace(&mut self, text: impl Into<String>) {
+        self.add(TextInputAction::Replace(text.into()));
+    }
+
+    /// Queue replacing every match of the search
+    pub fn replace_all(&mut self, text: impl Into<String>) {
+        self.add(TextInputAction::ReplaceAll(text.into()));
+    }
+
+    /// Queue ending the search
+    pub fn clear_search(&mut self) {
+        self.add(TextInputAction::ClearSearch);
+    }
+
+    /// Queue an edit deleting all the text in the buffer
+    pub fn clear(&mut self) {
+        self.add(TextInputAction::Edit(TextInputEdit::Clear));
+    }
+
     /// True if the queue is empty
     pub fn is_empty(&self) -> bool {
         self.actions.is_empty()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actions::apply_text_input_edit;
+    use crate::edit::cursor_at_byte_offset;
+    use bevy::text::cosmic_text::{Attrs, FontSystem, Shaping, fontdb};
+
+    /// Font system with only the monospace font from the examples' assets
+    pub(crate) fn test_font_system() -> FontSystem {
+        let mut db = fontdb::Database::new();
+        db.load_font_data(include_bytes!("../assets/fonts/FiraMono-Medium.ttf").to_vec());
+        db.set_sans_serif_family("Fira Mono");
+        FontSystem::new_with_locale_and_db("en-US".to_string(), db)
+    }
+
+    /// Editor containing `text` with the cursor at the byte offset `cursor`, selecting from `anchor` if given
+    pub(crate) fn test_editor(
+        font_system: &mut FontSystem,
+        text: &str,
+        anchor: Option<usize>,
+        cursor: usize,
+    ) -> Editor<'static> {
+        let mut buffer = Buffer::new(font_system, Metrics::new(20., 20.));
+        buffer.set_text(font_system, text, Attrs::new(), Shaping::Advanced);
+        let mut editor = Editor::new(buffer);
+        let (anchor, cursor) = editor.with_buffer(|buffer| {
+            (
+                anchor.map(|anchor| cursor_at_byte_offset(buffer, anchor)),
+                cursor_at_byte_offset(buffer, cursor),
+            )
+        });
+        if let Some(anchor) = anchor {
+            editor.set_selection(Selection::Normal(anchor));
+        }
+        editor.set_cursor(cursor);
+        editor
+    }
+
+    /// Applies `edit` to `text` with the cursor at `cursor`, selecting from `anchor` if given.
+    /// Returns the delta of the resulting change, after checking that it reverts to `text`.
+    fn delta_of(
+        text: &str,
+        anchor: Option<usize>,
+        cursor: usize,
+        edit: TextInputEdit,
+    ) -> TextDelta {
+        let mut font_system = test_font_system();
+        let mut editor = test_editor(&mut font_system, text, anchor, cursor);
+        let mut editor = editor.borrow_with(&mut font_system);
+
+        let change = apply_text_input_edit(
+            edit,
+            &mut editor,
+            &mut TextInputHistory::default(),
+            None,
+            &None,
+        )
+        .unwrap();
+        let new_text = editor.with_buffer(get_text);
+        let (old_text, delta) = TextDelta::from_change(&change, &new_text);
+        assert_eq!(old_text, text);
+        delta
+    }
+
+    fn delta(range: Range<usize>, text: &str) -> TextDelta {
+        TextDelta {
+            range,
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn delta_of_insert() {
+        let edit = TextInputEdit::Insert('!', false);
+        assert_eq!(delta_of("hello", None, 5, edit), delta(5..5, "!"));
+    }
+
+    #[test]
+    fn delta_of_delete() {
+        let edit = TextInputEdit::Delete;
+        assert_eq!(delta_of("hello", None, 1, edit), delta(1..2, ""));
+        let edit = TextInputEdit::Backspace;
+        assert_eq!(delta_of("ab\ncd", None, 3, edit), delta(2..3, ""));
+    }
+
+    #[test]
+    fn delta_of_replace() {
+        let edit = TextInputEdit::Paste("bye".to_string());
+        assert_eq!(
+            delta_of("hello world", Some(0), 5, edit),
+            delta(0..5, "bye")
+        );
+        let edit = TextInputEdit::Paste("x".to_string());
+        assert_eq!(delta_of("ab\ncd\nef", Some(1), 7, edit), delta(1..7, "x"));
+    }
+
+    #[test]
+    fn delta_of_multiple_replacements_covers_all_of_them() {
+        let edit =
+            TextInputEdit::ReplaceRanges(vec![(0..1, "xy".to_string()), (4..5, String::new())]);
+        assert_eq!(delta_of("a b c d", None, 0, edit), delta(0..5, "xy b "));
+    }
+
+    #[test]
+    fn delta_of_repeated_characters_is_at_the_cursor() {
+        let edit = TextInputEdit::Insert('a', false);
+        assert_eq!(delta_of("aa", None, 0, edit), delta(0..0, "a"));
+        let edit = TextInputEdit::Backspace;
+        assert_eq!(delta_of("aaa", None, 1, edit), delta(0..1, ""));
+    }
+
+    /// Checks that `filter` accepts the `accepted` texts and rejects the `rejected` texts
+    fn assert_filter(filter: TextInputFilter, accepted: &[&str], rejected: &[&str]) {
+        for text in accepted {
+            assert!(filter.is_match(text), "{filter:?} rejected {text:?}");
+        }
+        for text in rejected {
+            assert!(!filter.is_match(text), "{filter:?} accepted {text:?}");
+        }
+    }
+
+    #[test]
+    fn numeric_filters() {
+        assert_filter(
+            TextInputFilter::Integer,
+            &["", "-", "0", "-12", "0012"],
+            &["+1", "--1", "1-", "1.0", "1e5", " 1", "a"],
+        );
+        assert_filter(
+            TextInputFilter::Decimal,
+            &["", "-", ".", "-.", "-.5", "1.", "1.25", "-0.5", ".5"],
+            &["+1", "1..2", "1.2.3", "1e5", "-1e-5", "1-", "--1", "1,5"],
+        );
+        assert_filter(
+            TextInputFilter::Hex,
+            &["", "0", "deadBEEF", "0123456789abcdef"],
+            &["0x1f", "g", "-1", " a"],
+        );
+    }
+
+    #[test]
+    fn rejected_inserts_leave_the_text_cursor_and_history_unchanged() {
+        let mut font_system = test_font_system();
+        let mut editor = test_editor(&mut font_system, "12", None, 1);
+        let mut editor = editor.borrow_with(&mut font_system);
+        let mut history = TextInputHistory::default();
+        let filter = Some(TextInputFilter::Integer);
+
+        for edit in [
+            TextInputEdit::Insert('x', false),
+            TextInputEdit::Insert('x', true),
+            TextInputEdit::Paste("3a".to_string()),
+        ] {
+            let change = apply_text_input_edit(edit, &mut editor, &mut history, None, &filter);
+            assert!(change.is_none());
+            assert_eq!(editor.with_buffer(get_text), "12");
+            assert_eq!(editor.cursor(), Cursor::new(0, 1));
+            assert_eq!(editor.selection(), Selection::None);
+            assert!(!history.can_undo());
+        }
+
+        let edit = TextInputEdit::Insert('3', true);
+        assert!(apply_text_input_edit(edit, &mut editor, &mut history, None, &filter).is_some());
+        assert_eq!(editor.with_buffer(get_text), "13");
+        assert!(history.can_undo());
+    }
+
+    #[test]
+    fn text_filters() {
+        assert_filter(
+            TextInputFilter::Alphanumeric,
+            &["", "abc123", "Été", "日本語", "٣"],
+            &["a b", "a_b", "a-1", "!"],
+        );
+        assert_filter(
+            TextInputFilter::Identifier,
+            &["", "_", "a", "_a1", "snake_case", "ünï"],
+            &["1a", "a-b", "a b", "-"],
+        );
+        assert_filter(
+            TextInputFilter::Email,
+            &["", "user", "user@", "@host", "user.name+tag@example.com"],
+            &["a@b@c", "user name@host", "user@host ", "\tuser"],
+        );
+        assert_filter(
+            TextInputFilter::Ascii,
+            &["", "abc ~!\n", "\u{7f}"],
+            &["é", "日", "\u{80}"],
+        );
+    }
+
+    #[test]
+    fn regex_and_custom_filters() {
+        let ip = Regex::new(r"^[0-9]{0,3}(\.[0-9]{0,3}){0,3}$").unwrap();
+        let filter = TextInputFilter::Regex(ip);
+        assert!(filter.regex().is_some());
+        assert_filter(
+            filter,
+            &["", "192.168.", "10.0.0.1"],
+            &["1.2.3.4.5", "1234", "a"],
+        );
+
+        let filter = TextInputFilter::custom(|text| text.len() <= 3 && !text.contains('x'));
+        assert!(filter.regex().is_none());
+        assert_filter(filter, &["", "abc"], &["abcd", "x"]);
+    }
+}
@@ -6,9 +6,15 @@ use crate::TextInputPrompt;
 use crate::TextInputPromptLayoutInfo;
 use crate::TextInputStyle;
 use crate::edit::is_buffer_empty;
+use crate::gutter::TextInputGutterLayout;
+use crate::gutter::TextInputGutterStyle;
+use crate::highlight::TextInputHighlight;
+use crate::scrollbar::TextInputScrollbarLayout;
+use crate::scrollbar::TextInputScrollbarStyle;
 use bevy::asset::AssetId;
 use bevy::asset::Assets;
 use bevy::color::Alpha;
+use bevy::color::Color;
 use bevy::color::LinearRgba;
 use bevy::ecs::entity::Entity;
 use bevy::ecs::system::Commands;
@@ -17,6 +23,7 @@ use bevy::ecs::system::Res;
 use bevy::ecs::system::ResMut;
 use bevy::image::TextureAtlasLayout;
 use bevy::input_focus::InputFocus;
+use bevy::math::Affine3A;
 use bevy::math::Mat4;
 use bevy::math::Rect;
 use bevy::math::Vec2;
@@ -39,6 +46,49 @@ use bevy::ui::NodeType;
 use bevy::ui::ResolvedBorderRadius;
 use bevy::ui::UiCameraMap;
 
+/// The node that solid rects are extracted for by `push_rect`
+struct RectNode {
+    entity: Entity,
+    stack_index: u32,
+    clip: Option<Rect>,
+    extracted_camera_entity: Entity,
+}
+
+/// Extracts a solid colored rect, positioned by `transform` relative to the top left of the node
+fn push_rect(
+    commands: &mut Commands,
+    extracted_uinodes: &mut ExtractedUiNodes,
+    node: &RectNode,
+    transform: Affine3A,
+    rect: Rect,
+    color: LinearRgba,
+    border_radius: ResolvedBorderRadius,
+) {
+    extracted_uinodes.uinodes.push(ExtractedUiNode {
+        stack_index: node.stack_index,
+        color,
+        image: AssetId::default(),
+        clip: node.clip,
+        extracted_camera_entity: node.extracted_camera_entity,
+        rect: Rect {
+            min: Vec2::ZERO,
+            max: rect.size(),
+        },
+        item: ExtractedUiItem::Node {
+            atlas_scaling: None,
+            flip_x: false,
+            flip_y: false,
+            border_radius,
+            border: BorderRect::ZERO,
+            node_type: NodeType::Rect,
+            transform: transform * Mat4::from_translation(rect.center().extend(0.)),
+        },
+        main_entity: node.entity.into(),
+        render_entity: commands.spawn(TemporaryRenderEntity).id(),
+    });
+}
+
+#[allow(clippy::type_complexity)]
 pub fn extract_text_input_nodes(
     mut commands: Commands,
     mut extracted_uinodes: ResMut<ExtractedUiNodes>,
@@ -57,6 +107,8 @@ pub fn extract_text_input_nodes(
             &TextInputStyle,
             &TextInputNode,
             &TextInputBuffer,
+            Option<&TextInputHighlight>,
+            Option<&TextInputGutterLayout>,
         )>,
     >,
     camera_map: Extract<UiCameraMap>,
@@ -78,6 +130,8 @@ pub fn extract_text_input_nodes(
         style,
         input,
         input_buffer,
+        highlight,
+        gutter,
     ) in &uinode_query
     {
         // Skip if not visible or if size is set to zero (e.g. when a parent is set to `Display::None`)
@@ -90,60 +144,112 @@ pub fn extract_text_input_nodes(
         };
 
         let color = text_color.0.to_linear();
-        let selection_color = style
-            .selected_text_color
-            .map(|selection_color| selection_color.to_linear())
-            .unwrap_or(color);
+        let highlight = highlight.filter(|_| input.mode.mask().is_none());
+        let glyph_color = |span_index: usize, line_index: usize, byte_index: usize| {
+            input_buffer
+                .span_style(span_index)
+                .and_then(|span_style| span_style.color)
+                .or_else(|| highlight?.color_at(line_index, byte_index))
+                .map(|glyph_color| glyph_color.to_linear())
+                .unwrap_or(color)
+        };
 
-        let scroll = input_buffer
-            .editor
-            .with_buffer(|buffer| Vec2::new(buffer.scroll().horizontal, 0.)); // buffer.scroll().vertical));
+        // The layout is positioned relative to the top of the first visible line, see `text_input_pipeline::layout_runs`
+        let scroll = input_buffer.display_scroll();
+        let scroll = Vec2::new(scroll.horizontal, scroll.vertical);
+
+        // The text is drawn to the right of the gutter
+        let gutter_width = gutter.map_or(0., |gutter| gutter.width);
 
         let transform = global_transform.affine()
-            * bevy::math::Affine3A::from_translation((-0.5 * uinode.size() - scroll).extend(0.));
+            * bevy::math::Affine3A::from_translation(
+                (-0.5 * uinode.size() - scroll + gutter_width * Vec2::X).extend(0.),
+            );
 
-        let node_rect = Rect::from_center_size(
+        let mut node_rect = Rect::from_center_size(
             global_transform.translation().truncate(),
             uinode.size() * global_transform.scale().truncate(),
         );
+        node_rect.min.x =
+            (node_rect.min.x + gutter_width * global_transform.scale().x).min(node_rect.max.x);
 
         let clip = Some(
             clip.map(|clip| clip.clip.intersect(node_rect))
                 .unwrap_or(node_rect),
         );
 
+        let rect_node = RectNode {
+            entity,
+            stack_index: uinode.stack_index(),
+            clip,
+            extracted_camera_entity,
+        };
+
         let line_height = input_buffer
             .editor
             .with_buffer(|buffer| buffer.metrics().line_height);
 
+        let search_match_color = LinearRgba::from(style.search_match_color);
+        for rect in input_buffer.search_rects.iter() {
+            push_rect(
+                &mut commands,
+                &mut extracted_uinodes,
+                &rect_node,
+                transform,
+                *rect,
+                search_match_color,
+                ResolvedBorderRadius::ZERO,
+            );
+        }
+
         for (i, rect) in input_buffer.selection_rects.iter().enumerate() {
             let size = if (1..input_buffer.selection_rects.len()).contains(&i) {
                 rect.size() + Vec2::Y
             } else {
                 rect.size()
             } + 2. * Vec2::X;
-            extracted_uinodes.uinodes.push(ExtractedUiNode {
-                stack_index: uinode.stack_index(),
-                color: LinearRgba::from(style.selection_color),
-                image: AssetId::default(),
-                clip,
-                extracted_camera_entity,
-                rect: Rect {
-                    min: Vec2::ZERO,
-                    max: size,
-                },
-                item: ExtractedUiItem::Node {
-                    atlas_scaling: None,
-                    flip_x: false,
-                    flip_y: false,
-                    border_radius: ResolvedBorderRadius::ZERO,
-                    border: BorderRect::ZERO,
-                    node_type: NodeType::Rect,
-                    transform: transform * Mat4::from_translation(rect.center().extend(0.)),
-                },
-                main_entity: entity.into(),
-                render_entity: commands.spawn(TemporaryRenderEntity).id(),
-            });
+            push_rect(
+                &mut commands,
+                &mut extracted_uinodes,
+                &rect_node,
+                transform,
+                Rect::from_center_size(rect.center(), size),
+                LinearRgba::from(style.selection_color),
+                ResolvedBorderRadius::ZERO,
+            );
+        }
+
+        let preedit_underline_color = style
+            .preedit_underline_color
+            .map(|color| color.to_linear())
+            .unwrap_or(color);
+
+        for rect in input_buffer.preedit_rects.iter() {
+            push_rect(
+                &mut commands,
+                &mut extracted_uinodes,
+                &rect_node,
+                transform,
+                *rect,
+                preedit_underline_color,
+                ResolvedBorderRadius::ZERO,
+            );
+        }
+
+        for (rect, span_index) in input_buffer.decoration_rects.iter() {
+            push_rect(
+                &mut commands,
+                &mut extracted_uinodes,
+                &rect_node,
+                transform,
+                *rect,
+                input_buffer
+                    .span_style(*span_index)
+                    .and_then(|span_style| span_style.color)
+                    .map(|span_color| span_color.to_linear())
+                    .unwrap_or(color),
+                ResolvedBorderRadius::ZERO,
+            );
         }
 
         let cursor_visable = active_text_input.0.is_some_and(|active| active == entity)
@@ -151,32 +257,44 @@ pub fn extract_text_input_nodes(
             && input_buffer.cursor_blink_time < style.blink_interval
             && !style.cursor_color.is_fully_transparent();
 
-        let cursor_position = input_buffer
-            .editor
-            .cursor_position()
-            .filter(|_| cursor_visable);
+        let cursor_positions = if cursor_visable {
+            input_buffer
+                .display_cursor_position()
+                .into_iter()
+                .chain(
+                    input_buffer
+                        .carets
+                        .iter()
+                        .filter_map(|caret| input_buffer.display_position(caret.cursor)),
+                )
+                .collect()
+        } else {
+            vec![]
+        };
 
-        let selection = input_buffer.editor.selection_bounds();
+        let selections = input_buffer.selections();
 
         for TextInputGlyph {
             position,
             atlas_info,
-
+            span_index,
             line_index,
             byte_index,
             ..
         } in text_layout_info.glyphs.iter()
         {
-            let color_out = if let Some((s0, s1)) = selection {
-                if (s0.line < *line_index || (*line_index == s0.line && s0.index <= *byte_index))
+            let glyph_color = glyph_color(*span_index, *line_index, *byte_index);
+            let is_selected = selections.iter().any(|(s0, s1)| {
+                (s0.line < *line_index || (*line_index == s0.line && s0.index <= *byte_index))
                     && (*line_index < s1.line || (*line_index == s1.line && *byte_index < s1.index))
-                {
-                    selection_color
-                } else {
-                    color
-                }
+            });
+            let color_out = if is_selected {
+                style
+                    .selected_text_color
+                    .map(|selection_color| selection_color.to_linear())
+                    .unwrap_or(glyph_color)
             } else {
-                color
+                glyph_color
             };
 
             let Some(rect) = texture_atlases
@@ -207,7 +325,7 @@ pub fn extract_text_input_nodes(
             end += 1;
         }
 
-        if let Some((x, y)) = cursor_position {
+        for (x, y) in cursor_positions {
             let cursor_height = line_height * style.cursor_height;
 
             let x = x as f32;
@@ -216,37 +334,23 @@ pub fn extract_text_input_nodes(
             let scale_factor = uinode.inverse_scale_factor().recip();
             let width = style.cursor_width * scale_factor;
 
-            extracted_uinodes.uinodes.push(ExtractedUiNode {
-                stack_index: uinode.stack_index(),
+            push_rect(
+                &mut commands,
+                &mut extracted_uinodes,
+                &rect_node,
+                transform,
+                Rect::from_center_size(
+                    Vec2::new(x + 0.5 * width, y + 0.5 * line_height),
+                    Vec2::new(width, cursor_height),
+                ),
                 color,
-                image: AssetId::default(),
-                clip,
-                extracted_camera_entity,
-                rect: Rect {
-                    min: Vec2::ZERO,
-                    max: Vec2::new(width, cursor_height),
-                },
-                item: ExtractedUiItem::Node {
-                    atlas_scaling: None,
-                    flip_x: false,
-                    flip_y: false,
-                    border_radius: ResolvedBorderRadius::ZERO,
-                    border: BorderRect::ZERO,
-                    node_type: NodeType::Rect,
-                    transform: transform
-                        * Mat4::from_translation(Vec3::new(
-                            x + 0.5 * width,
-                            y + 0.5 * line_height,
-                            0.,
-                        )),
-                },
-                main_entity: entity.into(),
-                render_entity: commands.spawn(TemporaryRenderEntity).id(),
-            });
+                ResolvedBorderRadius::ZERO,
+            );
         }
     }
 }
 
+#[allow(clippy::type_complexity)]
 pub fn extract_text_input_prompts(
     mut commands: Commands,
     mut extracted_uinodes: ResMut<ExtractedUiNodes>,
@@ -263,6 +367,7 @@ pub fn extract_text_input_prompts(
             &TextColor,
             &TextInputBuffer,
             &TextInputPrompt,
+            Option<&TextInputGutterLayout>,
         )>,
     >,
     camera_map: Extract<UiCameraMap>,
@@ -283,10 +388,11 @@ pub fn extract_text_input_prompts(
         text_color,
         input,
         prompt,
+        gutter,
     ) in &uinode_query
     {
-        // only display the prompt if the text input is empty, including whitespace
-        if !input.editor.with_buffer(is_buffer_empty) {
+        // only display the prompt if the text input is empty, including whitespace, and there is no IME composition
+        if !input.editor.with_buffer(is_buffer_empty) || input.preedit.is_some() {
             continue;
         }
 
@@ -301,13 +407,19 @@ pub fn extract_text_input_prompts(
 
         let color = prompt.color.unwrap_or(text_color.0).to_linear();
 
+        let gutter_width = gutter.map_or(0., |gutter| gutter.width);
+
         let transform = global_transform.affine()
-            * bevy::math::Affine3A::from_translation((-0.5 * uinode.size()).extend(0.));
+            * bevy::math::Affine3A::from_translation(
+                (-0.5 * uinode.size() + gutter_width * Vec2::X).extend(0.),
+            );
 
-        let node_rect = Rect::from_center_size(
+        let mut node_rect = Rect::from_center_size(
             global_transform.translation().truncate(),
             uinode.size() * global_transform.scale().truncate(),
         );
+        node_rect.min.x =
+            (node_rect.min.x + gutter_width * global_transform.scale().x).min(node_rect.max.x);
 
         let clip = Some(
             clip.map(|clip| clip.clip.intersect(node_rect))
@@ -346,3 +458,237 @@ pub fn extract_text_input_prompts(
         }
     }
 }
+
+#[allow(clippy::type_complexity)]
+pub fn extract_text_input_scrollbars(
+    mut commands: Commands,
+    mut extracted_uinodes: ResMut<ExtractedUiNodes>,
+    uinode_query: Extract<
+        Query<(
+            Entity,
+            &ComputedNode,
+            &GlobalTransform,
+            &InheritedVisibility,
+            Option<&CalculatedClip>,
+            &ComputedNodeTarget,
+            &TextInputScrollbarStyle,
+            &TextInputScrollbarLayout,
+        )>,
+    >,
+    camera_map: Extract<UiCameraMap>,
+) {
+    let mut camera_mapper = camera_map.get_mapper();
+
+    for (entity, uinode, global_transform, inherited_visibility, clip, target, style, layout) in
+        &uinode_query
+    {
+        // Skip if not visible or if size is set to zero (e.g. when a parent is set to `Display::None`)
+        if !inherited_visibility.get() || uinode.is_empty() {
+            continue;
+        }
+
+        let Some(extracted_camera_entity) = camera_mapper.map(target) else {
+            continue;
+        };
+
+        let transform = global_transform.affine()
+            * bevy::math::Affine3A::from_translation((-0.5 * uinode.size()).extend(0.));
+
+        let node_rect = Rect::from_center_size(
+            global_transform.translation().truncate(),
+            uinode.size() * global_transform.scale().truncate(),
+        );
+
+        let clip = Some(
+            clip.map(|clip| clip.clip.intersect(node_rect))
+                .unwrap_or(node_rect),
+        );
+
+        let rect_node = RectNode {
+            entity,
+            stack_index: uinode.stack_index(),
+            clip,
+            extracted_camera_entity,
+        };
+
+        let scale_factor = uinode.inverse_scale_factor().recip();
+
+        for geometry in [layout.vertical, layout.horizontal].into_iter().flatten() {
+            let thumb_color = if layout.is_dragging(geometry.axis) {
+                style.thumb_drag_color
+            } else {
+                style.thumb_color
+            };
+            let thumb_radius =
+                (style.thumb_radius * scale_factor).min(0.5 * geometry.thumb.size().min_element());
+
+            for (rect, color, radius) in [
+                (geometry.track, style.track_color, 0.),
+                (geometry.thumb, thumb_color, thumb_radius),
+            ] {
+                if color == Color::NONE {
+                    continue;
+                }
+
+                push_rect(
+                    &mut commands,
+                    &mut extracted_uinodes,
+                    &rect_node,
+                    transform,
+                    rect,
+                    color.to_linear(),
+                    ResolvedBorderRadius {
+                        top_left: radius,
+                        top_right: radius,
+                        bottom_left: radius,
+                        bottom_right: radius,
+                    },
+                );
+            }
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+pub fn extract_text_input_gutters(
+    mut commands: Commands,
+    mut extracted_uinodes: ResMut<ExtractedUiNodes>,
+    texture_atlases: Extract<Res<Assets<TextureAtlasLayout>>>,
+    uinode_query: Extract<
+        Query<(
+            Entity,
+            &ComputedNode,
+            &GlobalTransform,
+            &InheritedVisibility,
+            Option<&CalculatedClip>,
+            &ComputedNodeTarget,
+            &TextInputBuffer,
+            &TextInputGutterStyle,
+            &TextInputGutterLayout,
+        )>,
+    >,
+    camera_map: Extract<UiCameraMap>,
+) {
+    let mut camera_mapper = camera_map.get_mapper();
+
+    let mut start = extracted_uinodes.glyphs.len();
+    let mut end = start + 1;
+
+    for (
+        entity,
+        uinode,
+        global_transform,
+        inherited_visibility,
+        clip,
+        target,
+        input_buffer,
+        style,
+        layout,
+    ) in &uinode_query
+    {
+        // Skip if not visible or if size is set to zero (e.g. when a parent is set to `Display::None`)
+        if !inherited_visibility.get() || uinode.is_empty() || layout.width <= 0. {
+            continue;
+        }
+
+        let Some(extracted_camera_entity) = camera_mapper.map(target) else {
+            continue;
+        };
+
+        let node_transform = global_transform.affine()
+            * bevy::math::Affine3A::from_translation((-0.5 * uinode.size()).extend(0.));
+
+        // Line numbers scroll vertically with the text
+        let scroll = input_buffer.display_scroll().vertical;
+        let transform = node_transform * bevy::math::Affine3A::from_translation(-scroll * Vec3::Y);
+
+        let mut node_rect = Rect::from_center_size(
+            global_transform.translation().truncate(),
+            uinode.size() * global_transform.scale().truncate(),
+        );
+        node_rect.max.x =
+            (node_rect.min.x + layout.width * global_transform.scale().x).min(node_rect.max.x);
+
+        let clip = Some(
+            clip.map(|clip| clip.clip.intersect(node_rect))
+                .unwrap_or(node_rect),
+        );
+
+        let rect_node = RectNode {
+            entity,
+            stack_index: uinode.stack_index(),
+            clip,
+            extracted_camera_entity,
+        };
+
+        let current_line = input_buffer.editor.cursor().line;
+
+        let background = Rect::new(0., 0., layout.width, uinode.size().y);
+        let current_line_backgrounds = layout
+            .numbers
+            .iter()
+            .filter(|number| number.line == current_line)
+            .map(|number| (number.rect, style.current_line_background_color, transform));
+
+        for (rect, color, transform) in [(background, style.background_color, node_transform)]
+            .into_iter()
+            .chain(current_line_backgrounds)
+        {
+            if color == Color::NONE {
+                continue;
+            }
+
+            push_rect(
+                &mut commands,
+                &mut extracted_uinodes,
+                &rect_node,
+                transform,
+                rect,
+                color.to_linear(),
+                ResolvedBorderRadius::ZERO,
+            );
+        }
+
+        let number_color = style.number_color.to_linear();
+        let current_line_color = style.current_line_color.to_linear();
+
+        for TextInputGlyph {
+            position,
+            atlas_info,
+            line_index,
+            ..
+        } in layout.glyphs.iter()
+        {
+            let Some(rect) = texture_atlases
+                .get(&atlas_info.texture_atlas)
+                .map(|atlas| atlas.textures[atlas_info.location.glyph_index].as_rect())
+            else {
+                continue;
+            };
+
+            extracted_uinodes.glyphs.push(ExtractedGlyph {
+                transform: transform * Mat4::from_translation(position.extend(0.)),
+                rect,
+            });
+
+            extracted_uinodes.uinodes.push(ExtractedUiNode {
+                stack_index: uinode.stack_index(),
+                color: if *line_index == current_line {
+                    current_line_color
+                } else {
+                    number_color
+                },
+                image: atlas_info.texture.id(),
+                clip,
+                rect,
+                extracted_camera_entity,
+                item: ExtractedUiItem::Glyphs { range: start..end },
+                main_entity: entity.into(),
+                render_entity: commands.spawn(TemporaryRenderEntity).id(),
+            });
+
+            start = end;
+            end += 1;
+        }
+    }
+}
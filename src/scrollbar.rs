@@ -0,0 +1,478 @@
+use crate::TextInputBuffer;
+use crate::TextInputNode;
+use crate::gutter::TextInputGutterLayout;
+use bevy::color::Color;
+use bevy::color::palettes::tailwind::GRAY_300;
+use bevy::color::palettes::tailwind::GRAY_500;
+use bevy::ecs::component::Component;
+use bevy::ecs::component::HookContext;
+use bevy::ecs::observer::Observer;
+use bevy::ecs::observer::Trigger;
+use bevy::ecs::system::Query;
+use bevy::ecs::world::DeferredWorld;
+use bevy::math::Rect;
+use bevy::math::Vec2;
+use bevy::picking::events::Drag;
+use bevy::picking::events::DragEnd;
+use bevy::picking::events::Pointer;
+use bevy::picking::events::Pressed;
+use bevy::picking::pointer::PointerButton;
+use bevy::prelude::ReflectComponent;
+use bevy::reflect::Reflect;
+use bevy::reflect::std_traits::ReflectDefault;
+use bevy::text::cosmic_text::Buffer;
+use bevy::text::cosmic_text::BufferLine;
+use bevy::text::cosmic_text::Edit;
+use bevy::transform::components::GlobalTransform;
+use bevy::ui::ComputedNode;
+
+/// When a scrollbar is shown
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Reflect)]
+#[reflect(Default, Debug, PartialEq)]
+pub enum ScrollbarVisibility {
+    /// Only show the scrollbar when the text overflows the input
+    #[default]
+    Auto,
+    /// Always show the scrollbar
+    Always,
+    /// Never show the scrollbar
+    Never,
+}
+
+impl ScrollbarVisibility {
+    fn is_visible(self, content_length: f32, viewport_length: f32) -> bool {
+        match self {
+            ScrollbarVisibility::Auto => viewport_length < content_length,
+            ScrollbarVisibility::Always => true,
+            ScrollbarVisibility::Never => false,
+        }
+    }
+}
+
+/// Scrollbars for a text input.
+/// Optional component.
+///
+/// The scrollbars are drawn over the text along the right and bottom edges of the input.
+/// Their thumbs can be dragged, and clicking a track scrolls by a page towards the pointer.
+/// Password inputs don't show scrollbars.
+#[derive(Component, Copy, Clone, Debug, Default, PartialEq, Reflect)]
+#[reflect(Component, Default, Debug, PartialEq)]
+#[require(TextInputScrollbarStyle, TextInputScrollbarLayout)]
+#[component(on_add = on_add_textinputscrollbar)]
+pub struct TextInputScrollbar {
+    /// Visibility of the vertical scrollbar
+    pub vertical: ScrollbarVisibility,
+    /// Visibility of the horizontal scrollbar
+    pub horizontal: ScrollbarVisibility,
+}
+
+/// Styling for the scrollbars of a text input
+#[derive(Component, Copy, Clone, Debug, PartialEq, Reflect)]
+#[reflect(Component, Default, Debug, PartialEq)]
+pub struct TextInputScrollbarStyle {
+    /// Thickness of the scrollbars in logical pixels
+    pub width: f32,
+    /// Minimum length of a thumb in logical pixels
+    pub min_thumb_length: f32,
+    /// Corner radius of the thumbs in logical pixels
+    pub thumb_radius: f32,
+    /// Color of the tracks
+    pub track_color: Color,
+    /// Color of the thumbs
+    pub thumb_color: Color,
+    /// Color of a thumb while it is dragged
+    pub thumb_drag_color: Color,
+}
+
+impl Default for TextInputScrollbarStyle {
+    fn default() -> Self {
+        Self {
+            width: 8.,
+            min_thumb_length: 16.,
+            thumb_radius: 4.,
+            track_color: Color::NONE,
+            thumb_color: GRAY_500.into(),
+            thumb_drag_color: GRAY_300.into(),
+        }
+    }
+}
+
+/// Axis of a scrollbar
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Reflect)]
+pub enum ScrollbarAxis {
+    Vertical,
+    Horizontal,
+}
+
+impl ScrollbarAxis {
+    /// The component of `value` along this axis
+    pub fn main(self, value: Vec2) -> f32 {
+        match self {
+            ScrollbarAxis::Vertical => value.y,
+            ScrollbarAxis::Horizontal => value.x,
+        }
+    }
+}
+
+/// Geometry of a scrollbar in physical pixels, relative to the top left of the text input
+#[derive(Copy, Clone, Debug, PartialEq, Reflect)]
+pub struct ScrollbarGeometry {
+    pub axis: ScrollbarAxis,
+    pub track: Rect,
+    pub thumb: Rect,
+    /// Length of the text along the scrollbar's axis
+    pub content_length: f32,
+    /// Length of the visible area along the scrollbar's axis
+    pub viewport_length: f32,
+    /// Distance the text is scrolled along the scrollbar's axis
+    pub offset: f32,
+}
+
+impl ScrollbarGeometry {
+    fn new(
+        axis: ScrollbarAxis,
+        track: Rect,
+        content_length: f32,
+        viewport_length: f32,
+        offset: f32,
+        min_thumb_length: f32,
+    ) -> Self {
+        let track_length = axis.main(track.size());
+        let thumb_length = if viewport_length < content_length {
+            (track_length * viewport_length / content_length)
+                .max(min_thumb_length)
+                .min(track_length)
+        } else {
+            track_length
+        };
+        let mut geometry = Self {
+            axis,
+            track,
+            thumb: track,
+            content_length,
+            viewport_length,
+            offset,
+        };
+        let max_offset = geometry.max_offset();
+        let t = if 0. < max_offset {
+            (offset / max_offset).clamp(0., 1.)
+        } else {
+            0.
+        };
+        let start = axis.main(track.min) + (track_length - thumb_length) * t;
+        geometry.thumb = match axis {
+            ScrollbarAxis::Vertical => {
+                Rect::new(track.min.x, start, track.max.x, start + thumb_length)
+            }
+            ScrollbarAxis::Horizontal => {
+                Rect::new(start, track.min.y, start + thumb_length, track.max.y)
+            }
+        };
+        geometry
+    }
+
+    /// Largest distance the text can be scrolled along the scrollbar's axis
+    pub fn max_offset(&self) -> f32 {
+        (self.content_length - self.viewport_length).max(0.)
+    }
+
+    /// The scroll offset that moves the start of the thumb to `thumb_start`
+    pub fn offset_at(&self, thumb_start: f32) -> f32 {
+        let free_length = self.axis.main(self.track.size()) - self.axis.main(self.thumb.size());
+        if free_length <= 0. {
+            return 0.;
+        }
+        let t = (thumb_start - self.axis.main(self.track.min)) / free_length;
+        (t * self.max_offset()).clamp(0., self.max_offset())
+    }
+}
+
+/// A thumb drag in progress
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(crate) struct ScrollbarDrag {
+    axis: ScrollbarAxis,
+    /// Distance from the start of the thumb to the pointer when the drag started
+    grab: f32,
+}
+
+/// Scrollbar geometry, updated by `update_text_input_scrollbars`
+#[derive(Component, Clone, Debug, Default)]
+pub struct TextInputScrollbarLayout {
+    pub vertical: Option<ScrollbarGeometry>,
+    pub horizontal: Option<ScrollbarGeometry>,
+    pub(crate) drag: Option<ScrollbarDrag>,
+    /// The last pointer press was on a scrollbar, so drags and clicks shouldn't select text
+    pub(crate) captured: bool,
+}
+
+impl TextInputScrollbarLayout {
+    /// The geometry of the scrollbar along an axis, if shown
+    pub fn get(&self, axis: ScrollbarAxis) -> Option<&ScrollbarGeometry> {
+        match axis {
+            ScrollbarAxis::Vertical => self.vertical.as_ref(),
+            ScrollbarAxis::Horizontal => self.horizontal.as_ref(),
+        }
+    }
+
+    /// The scrollbar with its track under `position`, in physical pixels relative to the top left of the text input
+    pub fn at(&self, position: Vec2) -> Option<&ScrollbarGeometry> {
+        [self.vertical.as_ref(), self.horizontal.as_ref()]
+            .into_iter()
+            .flatten()
+            .find(|geometry| geometry.track.contains(position))
+    }
+
+    /// True if a thumb is being dragged along `axis`
+    pub fn is_dragging(&self, axis: ScrollbarAxis) -> bool {
+        self.drag.is_some_and(|drag| drag.axis == axis)
+    }
+}
+
+fn on_add_textinputscrollbar(mut world: DeferredWorld, context: HookContext) {
+    for mut observer in [
+        Observer::new(on_scrollbar_pressed),
+        Observer::new(on_scrollbar_drag),
+        Observer::new(on_scrollbar_drag_end),
+    ] {
+        observer.watch_entity(context.entity);
+        world.commands().spawn(observer);
+    }
+}
+
+/// Height of a buffer line in pixels. Lines that haven't been laid out yet are assumed to fit on one line.
+fn buffer_line_height(line: &BufferLine, default_line_height: f32) -> f32 {
+    line.layout_opt().map_or(default_line_height, |layout| {
+        layout
+            .iter()
+            .map(|layout_line| layout_line.line_height_opt.unwrap_or(default_line_height))
+            .sum()
+    })
+}
+
+/// Size of the text in a buffer and the distance it is scrolled from the top left, in pixels
+fn scroll_extents(buffer: &Buffer) -> (Vec2, Vec2) {
+    let scroll = buffer.scroll();
+    let line_height = buffer.metrics().line_height;
+    let mut size = Vec2::ZERO;
+    let mut offset = Vec2::new(scroll.horizontal, 0.);
+    for (line_i, line) in buffer.lines.iter().enumerate() {
+        if line_i == scroll.line {
+            offset.y = size.y + scroll.vertical;
+        }
+        size.y += buffer_line_height(line, line_height);
+        if let Some(layout) = line.layout_opt() {
+            size.x = layout
+                .iter()
+                .fold(size.x, |width, layout_line| width.max(layout_line.w));
+        }
+    }
+    (size, offset)
+}
+
+/// Scroll a buffer so that the visible area starts `offset` pixels from the start of the text along `axis`
+fn set_scroll_offset(buffer: &mut Buffer, axis: ScrollbarAxis, offset: f32) {
+    let mut scroll = buffer.scroll();
+    match axis {
+        ScrollbarAxis::Horizontal => scroll.horizontal = offset,
+        ScrollbarAxis::Vertical => {
+            let line_height = buffer.metrics().line_height;
+            let mut line_top = 0.;
+            for (line_i, line) in buffer.lines.iter().enumerate() {
+                scroll.line = line_i;
+                scroll.vertical = offset - line_top;
+                let height = buffer_line_height(line, line_height);
+                if offset < line_top + height {
+                    break;
+                }
+                line_top += height;
+            }
+        }
+    }
+    buffer.set_scroll(scroll);
+}
+
+/// Updates the scrollbar geometry of text inputs after their text is laid out
+#[allow(clippy::type_complexity)]
+pub fn update_text_input_scrollbars(
+    mut query: Query<(
+        &TextInputNode,
+        &TextInputBuffer,
+        &TextInputScrollbar,
+        &TextInputScrollbarStyle,
+        &ComputedNode,
+        &mut TextInputScrollbarLayout,
+        Option<&TextInputGutterLayout>,
+    )>,
+) {
+    for (input, buffer, scrollbar, style, node, mut layout, gutter) in query.iter_mut() {
+        if input.mode.mask().is_some() {
+            layout.vertical = None;
+            layout.horizontal = None;
+            continue;
+        }
+
+        let scale_factor = node.inverse_scale_factor().recip();
+        let thickness = style.width * scale_factor;
+        let min_thumb_length = style.min_thumb_length * scale_factor;
+        let viewport = node.size();
+        // the horizontal scrollbar spans the text to the right of the gutter
+        let gutter_width = gutter.map_or(0., |gutter| gutter.width);
+        let (content, offset) = buffer.editor.with_buffer(scroll_extents);
+
+        let show_vertical = scrollbar.vertical.is_visible(content.y, viewport.y);
+        let show_horizontal = scrollbar
+            .horizontal
+            .is_visible(content.x, viewport.x - gutter_width);
+
+        // leave the bottom right corner empty if both scrollbars are shown
+        let vertical = show_vertical.then(|| {
+            let track = Rect::new(
+                viewport.x - thickness,
+                0.,
+                viewport.x,
+                viewport.y - if show_horizontal { thickness } else { 0. },
+            );
+            ScrollbarGeometry::new(
+                ScrollbarAxis::Vertical,
+                track,
+                content.y,
+                viewport.y,
+                offset.y,
+                min_thumb_length,
+            )
+        });
+        let horizontal = show_horizontal.then(|| {
+            let track = Rect::new(
+                gutter_width,
+                viewport.y - thickness,
+                viewport.x - if show_vertical { thickness } else { 0. },
+                viewport.y,
+            );
+            ScrollbarGeometry::new(
+                ScrollbarAxis::Horizontal,
+                track,
+                content.x,
+                viewport.x - gutter_width,
+                offset.x,
+                min_thumb_length,
+            )
+        });
+
+        if layout.vertical != vertical || layout.horizontal != horizontal {
+            layout.vertical = vertical;
+            layout.horizontal = horizontal;
+        }
+    }
+}
+
+fn on_scrollbar_pressed(
+    trigger: Trigger<Pointer<Pressed>>,
+    mut node_query: Query<(
+        &ComputedNode,
+        &GlobalTransform,
+        &mut TextInputBuffer,
+        &mut TextInputScrollbarLayout,
+        &TextInputNode,
+    )>,
+) {
+    if trigger.button != PointerButton::Primary {
+        return;
+    }
+
+    let Ok((node, transform, mut buffer, mut layout, input)) = node_query.get_mut(trigger.target)
+    else {
+        return;
+    };
+
+    if !input.is_enabled {
+        return;
+    }
+
+    let rect = Rect::from_center_size(transform.translation().truncate(), node.size());
+
+    let position =
+        trigger.pointer_location.position * node.inverse_scale_factor().recip() - rect.min;
+
+    layout.drag = None;
+    layout.captured = false;
+
+    let Some(geometry) = layout.at(position).copied() else {
+        return;
+    };
+
+    layout.captured = true;
+    let axis = geometry.axis;
+    if geometry.thumb.contains(position) {
+        layout.drag = Some(ScrollbarDrag {
+            axis,
+            grab: axis.main(position - geometry.thumb.min),
+        });
+    } else {
+        // page towards the pointer
+        let page = if axis.main(position) < axis.main(geometry.thumb.min) {
+            -geometry.viewport_length
+        } else {
+            geometry.viewport_length
+        };
+        let offset = (geometry.offset + page).clamp(0., geometry.max_offset());
+        buffer
+            .editor
+            .with_buffer_mut(|buffer| set_scroll_offset(buffer, axis, offset));
+    }
+}
+
+fn on_scrollbar_drag(
+    trigger: Trigger<Pointer<Drag>>,
+    mut node_query: Query<(
+        &ComputedNode,
+        &GlobalTransform,
+        &mut TextInputBuffer,
+        &TextInputScrollbarLayout,
+        &TextInputNode,
+    )>,
+) {
+    if trigger.button != PointerButton::Primary {
+        return;
+    }
+
+    let Ok((node, transform, mut buffer, layout, input)) = node_query.get_mut(trigger.target)
+    else {
+        return;
+    };
+
+    if !input.is_enabled {
+        return;
+    }
+
+    let Some(drag) = layout.drag else {
+        return;
+    };
+
+    let Some(geometry) = layout.get(drag.axis) else {
+        return;
+    };
+
+    let rect = Rect::from_center_size(transform.translation().truncate(), node.size());
+
+    let position =
+        trigger.pointer_location.position * node.inverse_scale_factor().recip() - rect.min;
+
+    let offset = geometry.offset_at(drag.axis.main(position) - drag.grab);
+    buffer
+        .editor
+        .with_buffer_mut(|buffer| set_scroll_offset(buffer, drag.axis, offset));
+}
+
+fn on_scrollbar_drag_end(
+    trigger: Trigger<Pointer<DragEnd>>,
+    mut node_query: Query<&mut TextInputScrollbarLayout>,
+) {
+    if trigger.button != PointerButton::Primary {
+        return;
+    }
+
+    if let Ok(mut layout) = node_query.get_mut(trigger.target) {
+        layout.drag = None;
+        layout.captured = false;
+    }
+}
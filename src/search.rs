@@ -0,0 +1,281 @@
+use std::ops::Range;
+
+use bevy::ecs::system::Query;
+use bevy::reflect::Reflect;
+use bevy::reflect::std_traits::ReflectDefault;
+use bevy::text::cosmic_text::Edit;
+use regex::Regex;
+use regex::RegexBuilder;
+
+use crate::TextInputBuffer;
+use crate::TextInputNode;
+
+/// Options for finding text in a text input, see `TextInputAction::Find`
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Reflect)]
+#[reflect(Default, Debug, PartialEq)]
+pub struct SearchOptions {
+    /// Only match text with the same case as the query
+    pub case_sensitive: bool,
+    /// Only match whole words. The ends of a plain query that are letters, digits or `_` must be at word boundaries,
+    /// so `a+` matches in `a+b` but not in `ba+`.
+    pub whole_word: bool,
+    /// The query is a regular expression.
+    /// Replacements can refer to its capture groups, for example `$1` or `${name}`.
+    pub regex: bool,
+}
+
+/// A search for text in a text input.
+///
+/// The matches are updated after each change to the input's text.
+#[derive(Clone, Debug)]
+pub struct TextInputSearch {
+    query: String,
+    options: SearchOptions,
+    /// `None` if the query is empty or isn't a valid regex
+    regex: Option<Regex>,
+    matches: Vec<Range<usize>>,
+    /// The text the matches were found in
+    text: String,
+    /// The `TextInputBuffer::text_generation` of the text the matches were found in
+    text_generation: u64,
+}
+
+impl TextInputSearch {
+    pub fn new(query: impl Into<String>, options: SearchOptions) -> Self {
+        let query = query.into();
+        let pattern = if options.regex {
+            query.clone()
+        } else {
+            regex::escape(&query)
+        };
+        let pattern = if options.whole_word {
+            // `\b` never matches next to a non-word character at the end of a plain query, such as in `a+`.
+            // Regex queries always get both boundaries.
+            let is_word = |c: Option<char>| c.is_some_and(|c| c.is_alphanumeric() || c == '_');
+            let start = options.regex || is_word(query.chars().next());
+            let end = options.regex || is_word(query.chars().next_back());
+            format!(
+                "{}(?:{pattern}){}",
+                if start { r"\b" } else { "" },
+                if end { r"\b" } else { "" }
+            )
+        } else {
+            pattern
+        };
+        let regex = (!query.is_empty())
+            .then(|| {
+                RegexBuilder::new(&pattern)
+                    .case_insensitive(!options.case_sensitive)
+                    .build()
+                    .ok()
+            })
+            .flatten();
+        Self {
+            query,
+            options,
+            regex,
+            matches: vec![],
+            text: String::new(),
+            text_generation: 0,
+        }
+    }
+
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    pub fn options(&self) -> SearchOptions {
+        self.options
+    }
+
+    /// False if the query is a regex that failed to compile
+    pub fn is_valid(&self) -> bool {
+        self.regex.is_some() || self.query.is_empty()
+    }
+
+    /// The byte ranges of the matches in the text returned by `TextInputBuffer::get_text`, in order
+    pub fn matches(&self) -> &[Range<usize>] {
+        &self.matches
+    }
+
+    /// Find the matches in `text`, returns true if they changed
+    pub(crate) fn update(&mut self, text: &str) -> bool {
+        if self.text == text {
+            return false;
+        }
+        self.text = text.to_string();
+        let matches = self
+            .regex
+            .as_ref()
+            .map(|regex| {
+                regex
+                    .find_iter(text)
+                    .map(|found| found.range())
+                    .filter(|range| !range.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default();
+        if self.matches == matches {
+            return false;
+        }
+        self.matches = matches;
+        true
+    }
+
+    /// The first match starting at or after `offset`, wrapping around to the first match
+    pub(crate) fn next_match(&self, offset: usize) -> Option<Range<usize>> {
+        self.matches
+            .iter()
+            .find(|range| offset <= range.start)
+            .or(self.matches.first())
+            .cloned()
+    }
+
+    /// The last match ending at or before `offset`, wrapping around to the last match
+    pub(crate) fn previous_match(&self, offset: usize) -> Option<Range<usize>> {
+        self.matches
+            .iter()
+            .rev()
+            .find(|range| range.end <= offset)
+            .or(self.matches.last())
+            .cloned()
+    }
+
+    /// The text replacing the match at `range`, with capture groups expanded for regex searches
+    pub(crate) fn replacement(&self, range: &Range<usize>, replacement: &str) -> String {
+        match self.regex.as_ref() {
+            Some(regex) if self.options.regex => regex
+                .captures_at(&self.text, range.start)
+                .filter(|captures| captures.get_match().range() == *range)
+                .map(|captures| {
+                    let mut expanded = String::new();
+                    captures.expand(replacement, &mut expanded);
+                    expanded
+                })
+                .unwrap_or_else(|| replacement.to_string()),
+            _ => replacement.to_string(),
+        }
+    }
+}
+
+/// Updates the matches of searches after the text of their input changes.
+/// Password inputs have no matches.
+pub fn update_text_input_searches(mut query: Query<(&TextInputNode, &mut TextInputBuffer)>) {
+    for (input, mut buffer) in query.iter_mut() {
+        if buffer
+            .search
+            .as_ref()
+            .is_none_or(|search| search.text_generation == buffer.text_generation)
+        {
+            continue;
+        }
+        let TextInputBuffer {
+            editor,
+            search,
+            text_generation,
+            ..
+        } = &mut *buffer;
+        if let Some(search) = search.as_mut() {
+            search.text_generation = *text_generation;
+            if input.mode.mask().is_none() && search.update(&editor.with_buffer(crate::get_text)) {
+                editor.set_redraw(true);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::single_range_in_vec_init)]
+mod tests {
+    use super::*;
+
+    fn search(query: &str, options: SearchOptions, text: &str) -> TextInputSearch {
+        let mut search = TextInputSearch::new(query, options);
+        search.update(text);
+        search
+    }
+
+    #[test]
+    fn update_finds_matches_when_the_text_changes() {
+        let mut search = TextInputSearch::new("ab", SearchOptions::default());
+        assert!(search.update("ab AB xab"));
+        assert_eq!(search.matches(), [0..2, 3..5, 7..9]);
+        assert!(!search.update("ab AB xab"));
+        // the text changed but the matches didn't
+        assert!(!search.update("ab AB xab "));
+        assert!(search.update("ab"));
+        assert_eq!(search.matches(), [0..2]);
+    }
+
+    #[test]
+    fn case_sensitive_and_invalid_queries() {
+        let options = SearchOptions {
+            case_sensitive: true,
+            ..Default::default()
+        };
+        assert_eq!(search("ab", options, "ab AB").matches(), [0..2]);
+
+        let options = SearchOptions {
+            regex: true,
+            ..Default::default()
+        };
+        let invalid = search("(ab", options, "(ab");
+        assert!(!invalid.is_valid());
+        assert!(invalid.matches().is_empty());
+        assert!(search("", options, "ab").matches().is_empty());
+    }
+
+    #[test]
+    fn whole_word_queries() {
+        let options = SearchOptions {
+            whole_word: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            search("ab", options, "ab abc cab ab").matches(),
+            [0..2, 11..13]
+        );
+        // boundaries are only required next to word characters
+        assert_eq!(search(".foo", options, "x.foo .foox").matches(), [1..5]);
+        assert_eq!(search("a+", options, "a+b ba+").matches(), [0..2]);
+        assert_eq!(search("++", options, "a++b").matches(), [1..3]);
+
+        let options = SearchOptions {
+            whole_word: true,
+            regex: true,
+            ..Default::default()
+        };
+        assert_eq!(search("a.", options, "ab abc").matches(), [0..2]);
+    }
+
+    #[test]
+    fn next_and_previous_matches_wrap_around() {
+        let search = search("ab", SearchOptions::default(), "ab ab ab");
+        assert_eq!(search.next_match(0), Some(0..2));
+        assert_eq!(search.next_match(1), Some(3..5));
+        assert_eq!(search.next_match(7), Some(0..2));
+        assert_eq!(search.previous_match(8), Some(6..8));
+        assert_eq!(search.previous_match(5), Some(3..5));
+        assert_eq!(search.previous_match(1), Some(6..8));
+
+        let empty = TextInputSearch::new("x", SearchOptions::default());
+        assert_eq!(empty.next_match(0), None);
+        assert_eq!(empty.previous_match(0), None);
+    }
+
+    #[test]
+    fn replacements_expand_capture_groups_of_regex_searches() {
+        let options = SearchOptions {
+            regex: true,
+            ..Default::default()
+        };
+        let search = search(r"(?<key>\w+)=(\d+)", options, "a=1 bc=23");
+        assert_eq!(search.matches(), [0..3, 4..9]);
+        assert_eq!(search.replacement(&(4..9), "$2:${key}"), "23:bc");
+        assert_eq!(search.replacement(&(0..3), "$$1"), "$1");
+
+        // plain searches insert the replacement as is
+        let search = self::search("a", SearchOptions::default(), "a");
+        assert_eq!(search.replacement(&(0..1), "$1"), "$1");
+    }
+}
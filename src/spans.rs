@@ -0,0 +1,248 @@
+use std::ops::Range;
+
+use bevy::asset::Handle;
+use bevy::color::Color;
+use bevy::text::Font;
+use bevy::text::cosmic_text::Buffer;
+use bevy::text::cosmic_text::Weight;
+
+use crate::edit::cursor_at_byte_offset;
+
+/// Style of a range of text in a text input.
+///
+/// Fields left unset use the text input's `TextFont` and `TextColor`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TextInputSpanStyle {
+    /// Text color
+    pub color: Option<Color>,
+    /// Font, the font's family, weight and style are used for the span
+    pub font: Option<Handle<Font>>,
+    /// Font size in logical pixels
+    pub font_size: Option<f32>,
+    /// Font weight, requires a font of the same family with a matching weight to be loaded
+    pub weight: Option<Weight>,
+    /// Italic text, requires a font of the same family with an italic face to be loaded
+    pub italic: bool,
+    /// Draw a line beneath the text
+    pub underline: bool,
+    /// Draw a line through the text
+    pub strikethrough: bool,
+}
+
+impl TextInputSpanStyle {
+    pub fn with_color(mut self, color: impl Into<Color>) -> Self {
+        self.color = Some(color.into());
+        self
+    }
+
+    pub fn with_font(mut self, font: Handle<Font>) -> Self {
+        self.font = Some(font);
+        self
+    }
+
+    pub fn with_font_size(mut self, font_size: f32) -> Self {
+        self.font_size = Some(font_size);
+        self
+    }
+
+    pub fn with_weight(mut self, weight: Weight) -> Self {
+        self.weight = Some(weight);
+        self
+    }
+
+    pub fn bold(self) -> Self {
+        self.with_weight(Weight::BOLD)
+    }
+
+    pub fn italic(mut self) -> Self {
+        self.italic = true;
+        self
+    }
+
+    pub fn underline(mut self) -> Self {
+        self.underline = true;
+        self
+    }
+
+    pub fn strikethrough(mut self) -> Self {
+        self.strikethrough = true;
+        self
+    }
+}
+
+/// Styles used by a buffer's spans.
+///
+/// The `Attrs::metadata` of each span in the buffer's lines identifies its style,
+/// `0` for unstyled text and `n` for the style at index `n - 1`.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct SpanStyles(Vec<TextInputSpanStyle>);
+
+impl SpanStyles {
+    /// The style identified by a span's metadata
+    pub fn get(&self, metadata: usize) -> Option<&TextInputSpanStyle> {
+        self.0.get(metadata.checked_sub(1)?)
+    }
+
+    /// Metadata identifying `style`, adding it to the styles if needed.
+    /// A new style replaces a style no longer used by any of the buffer's spans, so that overwritten styles don't accumulate.
+    fn metadata(&mut self, buffer: &Buffer, style: TextInputSpanStyle) -> usize {
+        if let Some(index) = self.0.iter().position(|existing| *existing == style) {
+            return index + 1;
+        }
+        let mut is_used = vec![false; self.0.len()];
+        for line in buffer.lines.iter() {
+            let attrs_list = line.attrs_list();
+            let metadata = attrs_list
+                .spans_iter()
+                .map(|(_, attrs)| attrs.metadata)
+                .chain([attrs_list.defaults().metadata]);
+            for metadata in metadata {
+                if let Some(is_used) = metadata
+                    .checked_sub(1)
+                    .and_then(|index| is_used.get_mut(index))
+                {
+                    *is_used = true;
+                }
+            }
+        }
+        match is_used.iter().position(|is_used| !is_used) {
+            Some(index) => {
+                self.0[index] = style;
+                index + 1
+            }
+            None => {
+                self.0.push(style);
+                self.0.len()
+            }
+        }
+    }
+
+    /// Styles along with their metadata
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &TextInputSpanStyle)> {
+        self.0
+            .iter()
+            .enumerate()
+            .map(|(index, style)| (index + 1, style))
+    }
+
+    /// Set the style of a byte range of the buffer's text. `None` removes any styling from the range.
+    pub fn set(
+        &mut self,
+        buffer: &mut Buffer,
+        range: Range<usize>,
+        style: Option<TextInputSpanStyle>,
+    ) {
+        if range.is_empty() {
+            return;
+        }
+        let start = cursor_at_byte_offset(buffer, range.start);
+        let end = cursor_at_byte_offset(buffer, range.end);
+        let metadata = style.map_or(0, |style| self.metadata(buffer, style));
+        for line_i in start.line..=end.line {
+            let line = &mut buffer.lines[line_i];
+            let line_start = if line_i == start.line { start.index } else { 0 };
+            let line_end = if line_i == end.line {
+                end.index
+            } else {
+                line.text().len()
+            };
+            let mut attrs_list = line.attrs_list().clone();
+            attrs_list.add_span(
+                line_start..line_end,
+                line.attrs_list().defaults().metadata(metadata),
+            );
+            line.set_attrs_list(attrs_list);
+        }
+        buffer.set_redraw(true);
+    }
+
+    /// The styled ranges of the buffer's text, as byte ranges into `TextInputBuffer::get_text`
+    pub fn spans(&self, buffer: &Buffer) -> Vec<(Range<usize>, TextInputSpanStyle)> {
+        let mut spans: Vec<(Range<usize>, TextInputSpanStyle)> = vec![];
+        let mut line_start = 0;
+        for line in buffer.lines.iter() {
+            for (range, attrs) in line.attrs_list().spans_iter() {
+                let Some(style) = self.get(attrs.metadata) else {
+                    continue;
+                };
+                let range = line_start + range.start..line_start + range.end;
+                // join spans continuing across line breaks or split by the buffer
+                match spans.last_mut() {
+                    Some((last_range, last_style))
+                        if last_style == style
+                            && (last_range.end == range.start
+                                || last_range.end + 1 == range.start
+                                    && range.start == line_start) =>
+                    {
+                        last_range.end = range.end;
+                    }
+                    _ => spans.push((range, style.clone())),
+                }
+            }
+            // skip the newline separating the lines
+            line_start += line.text().len() + 1;
+        }
+        spans
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::test_font_system;
+    use bevy::color::palettes::css::BLUE;
+    use bevy::color::palettes::css::GREEN;
+    use bevy::color::palettes::css::RED;
+    use bevy::text::cosmic_text::Attrs;
+    use bevy::text::cosmic_text::Metrics;
+    use bevy::text::cosmic_text::Shaping;
+
+    fn color(color: impl Into<Color>) -> TextInputSpanStyle {
+        TextInputSpanStyle {
+            color: Some(color.into()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn overwritten_styles_are_reused() {
+        let mut font_system = test_font_system();
+        let mut buffer = Buffer::new(&mut font_system, Metrics::new(20., 20.));
+        buffer.set_text(&mut font_system, "ab cd", Attrs::new(), Shaping::Advanced);
+        let mut styles = SpanStyles::default();
+
+        styles.set(&mut buffer, 3..5, Some(color(BLUE)));
+        for index in 0..10 {
+            let style = color(Color::srgb(index as f32 / 10., 0., 0.));
+            styles.set(&mut buffer, 0..2, Some(style));
+        }
+        // the blue style, the current style and the style it replaced
+        assert_eq!(styles.0.len(), 3);
+
+        styles.set(&mut buffer, 0..2, Some(color(RED)));
+        styles.set(&mut buffer, 0..2, Some(color(GREEN)));
+        assert_eq!(styles.0.len(), 3);
+        assert_eq!(
+            styles.spans(&buffer),
+            [(0..2, color(GREEN)), (3..5, color(BLUE))]
+        );
+    }
+
+    #[test]
+    fn identical_styles_are_shared() {
+        let mut font_system = test_font_system();
+        let mut buffer = Buffer::new(&mut font_system, Metrics::new(20., 20.));
+        buffer.set_text(&mut font_system, "ab\ncd", Attrs::new(), Shaping::Advanced);
+        let mut styles = SpanStyles::default();
+        styles.set(&mut buffer, 0..1, Some(color(RED)));
+        styles.set(&mut buffer, 4..5, Some(color(RED)));
+        assert_eq!(styles.0.len(), 1);
+        assert_eq!(
+            styles.spans(&buffer),
+            [(0..1, color(RED)), (4..5, color(RED))]
+        );
+
+        styles.set(&mut buffer, 0..5, None);
+        assert!(styles.spans(&buffer).is_empty());
+    }
+}
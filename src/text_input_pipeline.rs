@@ -1,12 +1,21 @@
+use crate::DisplayText;
+use crate::DisplayTextKind;
 use crate::TextInputBuffer;
 use crate::TextInputGlyph;
 use crate::TextInputLayoutInfo;
 use crate::TextInputNode;
 use crate::TextInputPrompt;
 use crate::TextInputPromptLayoutInfo;
+use crate::edit::cursor_at_byte_offset;
+use crate::gutter::TextInputGutter;
+use crate::gutter::TextInputGutterLayout;
+use crate::gutter::TextInputGutterStyle;
+use crate::gutter::digits_width;
+use crate::gutter::update_gutter_layout;
 use bevy::asset::AssetEvent;
 use bevy::asset::AssetId;
 use bevy::asset::Assets;
+use bevy::asset::Handle;
 use bevy::ecs::change_detection::DetectChanges;
 use bevy::ecs::event::EventReader;
 use bevy::ecs::resource::Resource;
@@ -23,6 +32,7 @@ use bevy::platform::collections::HashMap;
 use bevy::text::Font;
 use bevy::text::FontAtlasSet;
 use bevy::text::FontSmoothing;
+use bevy::text::GlyphAtlasInfo;
 use bevy::text::LineBreak;
 use bevy::text::LineHeight;
 use bevy::text::TextBounds;
@@ -30,8 +40,14 @@ use bevy::text::TextError;
 use bevy::text::TextFont;
 use bevy::text::YAxisOrientation;
 use bevy::text::cosmic_text;
+use bevy::text::cosmic_text::Attrs;
+use bevy::text::cosmic_text::AttrsList;
+use bevy::text::cosmic_text::AttrsOwned;
 use bevy::text::cosmic_text::Buffer;
+use bevy::text::cosmic_text::Cursor;
 use bevy::text::cosmic_text::Edit;
+use bevy::text::cosmic_text::LayoutGlyph;
+use bevy::text::cosmic_text::LayoutRun;
 use bevy::text::cosmic_text::Metrics;
 use bevy::ui::ComputedNode;
 use std::sync::Arc;
@@ -66,12 +82,11 @@ struct FontFaceInfo {
 }
 
 fn load_font_to_fontdb(
-    text_font: &TextFont,
+    font_handle: &Handle<Font>,
     font_system: &mut cosmic_text::FontSystem,
     map_handle_to_font_id: &mut HashMap<AssetId<Font>, (cosmic_text::fontdb::ID, Arc<str>)>,
     fonts: &Assets<Font>,
 ) -> FontFaceInfo {
-    let font_handle = text_font.font.clone();
     let (face_id, family_name) = map_handle_to_font_id
         .entry(font_handle.id())
         .or_insert_with(|| {
@@ -110,6 +125,154 @@ fn buffer_dimensions(buffer: &cosmic_text::Buffer) -> Vec2 {
     Vec2::new(width, height).ceil()
 }
 
+/// Layout runs of the visible lines of a buffer.
+///
+/// Unlike `Buffer::layout_runs`, the runs are positioned relative to the top of the first visible line
+/// (`Scroll::line`) without subtracting `Scroll::vertical`, and lines only partly scrolled into view are included.
+/// The vertical scroll offset is applied when rendering, the same as the horizontal offset.
+pub(crate) fn layout_runs(buffer: &Buffer) -> impl Iterator<Item = LayoutRun<'_>> {
+    indexed_layout_runs(buffer).map(|(_, run)| run)
+}
+
+/// `layout_runs` along with the index of each run's layout line within its buffer line
+pub(crate) fn indexed_layout_runs(buffer: &Buffer) -> impl Iterator<Item = (usize, LayoutRun<'_>)> {
+    let scroll = buffer.scroll();
+    let height = buffer.size().1;
+    let default_line_height = buffer.metrics().line_height;
+    let mut line_top = 0.;
+    buffer
+        .lines
+        .iter()
+        .enumerate()
+        .skip(scroll.line)
+        .map_while(|(line_i, line)| Some((line_i, line, line.shape_opt()?, line.layout_opt()?)))
+        .flat_map(|(line_i, line, shape, layout)| {
+            layout
+                .iter()
+                .enumerate()
+                .map(move |(layout_i, layout_line)| (line_i, line, shape, layout_i, layout_line))
+        })
+        .map_while(move |(line_i, line, shape, layout_i, layout_line)| {
+            if height.is_some_and(|height| height <= line_top - scroll.vertical) {
+                return None;
+            }
+            let line_height = layout_line.line_height_opt.unwrap_or(default_line_height);
+            let glyph_height = layout_line.max_ascent + layout_line.max_descent;
+            let run = LayoutRun {
+                line_i,
+                text: line.text(),
+                rtl: shape.rtl,
+                glyphs: &layout_line.glyphs,
+                line_y: line_top + (line_height - glyph_height) / 2. + layout_line.max_ascent,
+                line_top,
+                line_height,
+                line_w: layout_line.w,
+            };
+            line_top += line_height;
+            Some((layout_i, run))
+        })
+        .filter(move |(_, run)| scroll.vertical < run.line_top + run.line_height)
+}
+
+/// Position of a cursor in the coordinates used by `layout_runs`
+pub(crate) fn cursor_position(buffer: &Buffer, cursor: Cursor) -> Option<(i32, i32)> {
+    let mut position = None;
+    for run in layout_runs(buffer).filter(|run| run.line_i == cursor.line) {
+        if let Some(glyph) = run.glyphs.iter().find(|glyph| cursor.index <= glyph.start) {
+            return Some((glyph.x as i32, run.line_top as i32));
+        }
+        let x = run.glyphs.last().map_or(0., |glyph| glyph.x + glyph.w);
+        position = Some((x as i32, run.line_top as i32));
+    }
+    position
+}
+
+/// Cursor under a position relative to the top left of the visible area of a buffer
+pub(crate) fn hit(buffer: &Buffer, position: Vec2) -> Option<Cursor> {
+    let scroll = buffer.scroll();
+    let x = position.x + scroll.horizontal;
+    let y = position.y + scroll.vertical;
+    let height = buffer.size().1.unwrap_or(f32::INFINITY);
+    let mut last = None;
+    for run in layout_runs(buffer) {
+        if y < run.line_top + run.line_height {
+            let line_y = run.line_y - scroll.vertical;
+            // `Buffer::hit` only finds lines with their baseline inside the visible area
+            return if (0. ..=height).contains(&line_y) {
+                buffer.hit(x, position.y)
+            } else {
+                Some(run_hit(&run, x))
+            };
+        }
+        last = Some(run);
+    }
+    last.map(|run| run_hit(&run, f32::INFINITY))
+}
+
+/// Cursor nearest to `x` in a layout run
+pub(crate) fn run_hit(run: &LayoutRun, x: f32) -> Cursor {
+    match run.glyphs.iter().find(|glyph| x < glyph.x + glyph.w / 2.) {
+        Some(glyph) => Cursor::new(run.line_i, glyph.start),
+        None => Cursor::new_with_affinity(
+            run.line_i,
+            run.glyphs.last().map_or(0, |glyph| glyph.end),
+            cosmic_text::Affinity::Before,
+        ),
+    }
+}
+
+/// Adds a glyph to its font atlas if needed.
+/// Returns the glyph's atlas info, the position of its center relative to the top left of its layout
+/// with its baseline at `line_y`, and its size.
+pub(crate) fn place_glyph(
+    layout_glyph: &LayoutGlyph,
+    line_y: f32,
+    font_id: AssetId<Font>,
+    font_smoothing: FontSmoothing,
+    text_input_pipeline: &mut TextInputPipeline,
+    texture_atlases: &mut Assets<TextureAtlasLayout>,
+    textures: &mut Assets<Image>,
+) -> Result<(GlyphAtlasInfo, Vec2, UVec2), TextError> {
+    let TextInputPipeline {
+        font_system,
+        swash_cache,
+        font_atlas_sets,
+        ..
+    } = text_input_pipeline;
+
+    let font_atlas_set = font_atlas_sets.entry(font_id).or_default();
+
+    let physical_glyph = layout_glyph.physical((0., 0.), 1.);
+
+    let atlas_info = font_atlas_set
+        .get_glyph_atlas_info(physical_glyph.cache_key, font_smoothing)
+        .map(Ok)
+        .unwrap_or_else(|| {
+            font_atlas_set.add_glyph_to_atlas(
+                texture_atlases,
+                textures,
+                font_system,
+                swash_cache,
+                layout_glyph,
+                font_smoothing,
+            )
+        })?;
+
+    let texture_atlas = texture_atlases.get(&atlas_info.texture_atlas).unwrap();
+    let location = atlas_info.location;
+    let glyph_rect = texture_atlas.textures[location.glyph_index];
+    let left = location.offset.x as f32;
+    let top = location.offset.y as f32;
+    let glyph_size = UVec2::new(glyph_rect.width(), glyph_rect.height());
+
+    // offset by half the size because the origin is center
+    let x = glyph_size.x as f32 / 2.0 + left + physical_glyph.x as f32;
+    let y = line_y.round() + physical_glyph.y as f32 - top + glyph_size.y as f32 / 2.0;
+
+    Ok((atlas_info, Vec2::new(x, y), glyph_size))
+}
+
+#[allow(clippy::type_complexity)]
 pub fn text_input_system(
     mut textures: ResMut<Assets<Image>>,
     fonts: Res<Assets<Font>>,
@@ -121,12 +284,33 @@ pub fn text_input_system(
         &mut TextInputLayoutInfo,
         &mut TextInputBuffer,
         Ref<TextInputNode>,
+        Option<(
+            Ref<TextInputGutter>,
+            Ref<TextInputGutterStyle>,
+            &mut TextInputGutterLayout,
+        )>,
     )>,
 ) {
-    for (node, text_font, text_input_layout_info, mut editor, input) in text_query.iter_mut() {
+    for (node, text_font, text_input_layout_info, mut editor, input, mut gutter) in
+        text_query.iter_mut()
+    {
         let layout_info = text_input_layout_info.into_inner();
         let y_axis_orientation = YAxisOrientation::TopToBottom;
-        if editor.needs_update || text_font.is_changed() || node.is_changed() || input.is_changed()
+        let scale_factor = node.inverse_scale_factor().recip();
+
+        // the gutter is resized when the number of digits in its largest line number changes
+        let gutter_digits = gutter
+            .as_ref()
+            .map(|(gutter, ..)| editor.editor.with_buffer(|buffer| gutter.digits(buffer)));
+        let gutter_changed = gutter.as_ref().is_some_and(|(gutter, style, layout)| {
+            gutter.is_changed() || style.is_changed() || Some(layout.digits) != gutter_digits
+        });
+
+        if editor.needs_update
+            || text_font.is_changed()
+            || node.is_changed()
+            || input.is_changed()
+            || gutter_changed
         {
             let bounds = TextBounds {
                 width: Some(node.size().x),
@@ -138,6 +322,7 @@ pub fn text_input_system(
                 LineHeight::RelativeToFont(r) => r * text_font.font_size,
             };
 
+            let span_styles = editor.span_styles.clone();
             let result = editor.editor.with_buffer_mut(|buffer| {
                 let TextInputPipeline {
                     font_system,
@@ -148,19 +333,19 @@ pub fn text_input_system(
                     return Err(TextError::NoSuchFont);
                 }
 
-                let face_info =
-                    load_font_to_fontdb(&text_font, font_system, map_handle_to_font_id, &fonts);
+                let face_info = load_font_to_fontdb(
+                    &text_font.font,
+                    font_system,
+                    map_handle_to_font_id,
+                    &fonts,
+                );
 
-                let mut metrics = Metrics::new(text_font.font_size, line_height)
-                    .scale(node.inverse_scale_factor().recip());
+                let mut metrics =
+                    Metrics::new(text_font.font_size, line_height).scale(scale_factor);
 
                 metrics.font_size = metrics.font_size.max(0.000001);
                 metrics.line_height = metrics.line_height.max(0.000001);
 
-                buffer.set_metrics_and_size(font_system, metrics, bounds.width, bounds.height);
-
-                buffer.set_wrap(font_system, input.mode.wrap());
-
                 let attrs = cosmic_text::Attrs::new()
                     .metadata(0)
                     .family(cosmic_text::Family::Name(&face_info.family_name))
@@ -169,10 +354,75 @@ pub fn text_input_system(
                     .weight(face_info.weight)
                     .metrics(metrics);
 
-                let text = crate::get_text(buffer);
-                buffer.set_text(font_system, &text, attrs, cosmic_text::Shaping::Advanced);
+                // The gutter is on the left of the node, the text fills the remaining width
+                let gutter_width = match gutter.as_mut() {
+                    Some((gutter, style, layout)) => {
+                        let digits = gutter_digits.unwrap_or(gutter.min_digits);
+                        layout.digits = digits;
+                        layout.width = digits_width(font_system, metrics, attrs, digits)
+                            + 2. * style.padding * scale_factor;
+                        layout.width
+                    }
+                    None => 0.,
+                };
+
+                buffer.set_metrics_and_size(
+                    font_system,
+                    metrics,
+                    bounds.width.map(|width| (width - gutter_width).max(0.)),
+                    bounds.height,
+                );
+
+                buffer.set_wrap(font_system, input.mode.wrap());
+
+                // Attributes for each span style, the style's fields override the `TextFont`
+                let mut span_attrs = HashMap::<usize, AttrsOwned>::default();
+                for (metadata, style) in span_styles.iter() {
+                    let face_info = match &style.font {
+                        Some(font) => {
+                            if !fonts.contains(font.id()) {
+                                return Err(TextError::NoSuchFont);
+                            }
+                            load_font_to_fontdb(font, font_system, map_handle_to_font_id, &fonts)
+                        }
+                        None => face_info.clone(),
+                    };
+                    let font_size = style.font_size.unwrap_or(text_font.font_size);
+                    let line_height = match text_font.line_height {
+                        LineHeight::Px(h) => h,
+                        LineHeight::RelativeToFont(r) => r * font_size,
+                    };
+                    let mut span_metrics = Metrics::new(font_size, line_height).scale(scale_factor);
+                    span_metrics.font_size = span_metrics.font_size.max(0.000001);
+                    span_metrics.line_height = span_metrics.line_height.max(0.000001);
+
+                    let mut span = Attrs::new()
+                        .metadata(metadata)
+                        .family(cosmic_text::Family::Name(&face_info.family_name))
+                        .stretch(face_info.stretch)
+                        .style(face_info.style)
+                        .weight(style.weight.unwrap_or(face_info.weight))
+                        .metrics(span_metrics);
+                    if style.italic {
+                        span = span.style(cosmic_text::Style::Italic);
+                    }
+                    span_attrs.insert(metadata, AttrsOwned::new(span));
+                }
+
+                if buffer.lines.is_empty() {
+                    buffer.set_text(font_system, "", attrs, cosmic_text::Shaping::Advanced);
+                }
+
+                // Rebuild the attributes of each line, keeping the styles of its spans
                 let align = Some(input.justification.into());
                 for buffer_line in buffer.lines.iter_mut() {
+                    let mut attrs_list = AttrsList::new(attrs);
+                    for (range, span) in buffer_line.attrs_list().spans_iter() {
+                        if let Some(span) = span_attrs.get(&span.metadata) {
+                            attrs_list.add_span(range.clone(), span.as_attrs());
+                        }
+                    }
+                    buffer_line.set_attrs_list(attrs_list);
                     buffer_line.set_align(align);
                 }
 
@@ -192,22 +442,123 @@ pub fn text_input_system(
             .editor
             .shape_as_needed(&mut text_input_pipeline.font_system, false);
 
-        let selection = editor.editor.selection_bounds();
+        let selections = editor.selections();
+        let cursor = editor.editor.cursor();
         let TextInputBuffer {
             editor,
             selection_rects,
+            display_text,
+            preedit,
+            preedit_rects,
+            span_styles,
+            decoration_rects,
+            search,
+            search_rects,
             ..
         } = &mut *editor;
 
         if editor.redraw() {
             layout_info.glyphs.clear();
             selection_rects.clear();
+            preedit_rects.clear();
+            decoration_rects.clear();
+            search_rects.clear();
+
+            let display_kind = match (input.mode.mask(), preedit.as_ref()) {
+                (Some(mask), _) => Some(DisplayTextKind::Masked { mask }),
+                (None, Some(preedit)) => Some(DisplayTextKind::Preedit {
+                    preedit: preedit.clone(),
+                    position: cursor,
+                }),
+                (None, None) => None,
+            };
+
+            match (display_kind, display_text.as_mut()) {
+                (Some(kind), Some(display_text)) => display_text.kind = kind,
+                (Some(kind), None) => {
+                    *display_text = Some(DisplayText {
+                        kind,
+                        buffer: Buffer::new_empty(editor.with_buffer(|buffer| buffer.metrics())),
+                    });
+                }
+                (None, _) => *display_text = None,
+            }
+
+            // thickness of the IME preedit underline
+            let underline = node.inverse_scale_factor().recip();
 
             let result = editor.with_buffer_mut(|buffer| {
-                let box_size = buffer_dimensions(buffer);
-                let result = buffer.layout_runs().try_for_each(|run| {
-                    if let Some(selection) = selection {
-                        if let Some((x0, w)) = run.highlight(selection.0, selection.1) {
+                if let Some(display_text) = display_text.as_mut() {
+                    update_display_text(
+                        display_text,
+                        buffer,
+                        cursor,
+                        &mut text_input_pipeline.font_system,
+                    );
+                }
+                let buffer: &Buffer = buffer;
+                let display_text = display_text.as_ref();
+
+                // Password inputs and inputs with an IME composition display
+                // their `DisplayText` instead of the buffer's contents
+                let (layout_buffer, selections) = match display_text {
+                    Some(display_text) => (
+                        &display_text.buffer,
+                        selections
+                            .iter()
+                            .map(|(start, end)| {
+                                (
+                                    display_text.display_cursor(buffer, *start),
+                                    display_text.display_cursor(buffer, *end),
+                                )
+                            })
+                            .collect(),
+                    ),
+                    None => (buffer, selections),
+                };
+
+                let preedit_ranges = match display_text.map(|display_text| &display_text.kind) {
+                    Some(DisplayTextKind::Preedit { preedit, position }) => {
+                        let at = |index: usize| Cursor::new(position.line, position.index + index);
+                        let mut ranges = vec![(at(0), at(preedit.text.len()), underline)];
+                        if let Some((start, end)) = preedit.cursor
+                            && start != end
+                        {
+                            // The IME's target clause is underlined more heavily
+                            ranges.push((at(start), at(end), 2. * underline));
+                        }
+                        ranges
+                    }
+                    _ => vec![],
+                };
+
+                // The matches of the search, as cursors into the layout buffer
+                let search_matches = search
+                    .as_ref()
+                    .map(|search| {
+                        search
+                            .matches()
+                            .iter()
+                            .map(|range| {
+                                let cursor = |offset: usize| {
+                                    let cursor = cursor_at_byte_offset(buffer, offset);
+                                    match display_text {
+                                        Some(display_text) => {
+                                            display_text.display_cursor(buffer, cursor)
+                                        }
+                                        None => cursor,
+                                    }
+                                };
+                                (cursor(range.start), cursor(range.end))
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                    .unwrap_or_default();
+
+                let box_size = buffer_dimensions(layout_buffer);
+                let result = layout_runs(layout_buffer).try_for_each(|run| {
+                    for (start, end) in selections.iter() {
+                        if let Some((x0, w)) = run.highlight(*start, *end) {
                             let y0 = run.line_top;
                             let y1 = y0 + run.line_height;
                             let x1 = x0 + w;
@@ -216,14 +567,33 @@ pub fn text_input_system(
                         }
                     }
 
-                    let result = run
-                        .glyphs
+                    for (start, end) in search_matches
+                        .iter()
+                        .filter(|(start, end)| start.line <= run.line_i && run.line_i <= end.line)
+                    {
+                        if let Some((x0, w)) = run.highlight(*start, *end) {
+                            let y0 = run.line_top;
+                            search_rects.push(Rect::new(x0, y0, x0 + w, y0 + run.line_height));
+                        }
+                    }
+
+                    for (start, end, thickness) in preedit_ranges.iter() {
+                        if let Some((x0, w)) = run.highlight(*start, *end) {
+                            let y1 = run.line_top + run.line_height;
+                            preedit_rects.push(Rect::new(x0, y1 - thickness, x0 + w, y1));
+                        }
+                    }
+
+                    run.glyphs
                         .iter()
                         .map(move |layout_glyph| (layout_glyph, run.line_y, run.line_i))
                         .try_for_each(|(layout_glyph, line_y, line_i)| {
                             let mut temp_glyph;
                             let span_index = layout_glyph.metadata;
-                            let font_id = text_font.font.id();
+                            let font_id = span_styles
+                                .get(span_index)
+                                .and_then(|style| style.font.as_ref())
+                                .map_or(text_font.font.id(), Handle::id);
                             let font_smoothing = text_font.font_smoothing;
 
                             let layout_glyph = if font_smoothing == FontSmoothing::None {
@@ -243,69 +613,102 @@ pub fn text_input_system(
                                 layout_glyph
                             };
 
-                            let TextInputPipeline {
-                                font_system,
-                                swash_cache,
-                                font_atlas_sets,
-                                ..
-                            } = &mut *text_input_pipeline;
-
-                            let font_atlas_set = font_atlas_sets.entry(font_id).or_default();
-
-                            let physical_glyph = layout_glyph.physical((0., 0.), 1.);
-
-                            let atlas_info = font_atlas_set
-                                .get_glyph_atlas_info(physical_glyph.cache_key, font_smoothing)
-                                .map(Ok)
-                                .unwrap_or_else(|| {
-                                    font_atlas_set.add_glyph_to_atlas(
-                                        &mut texture_atlases,
-                                        &mut textures,
-                                        font_system,
-                                        swash_cache,
-                                        layout_glyph,
-                                        font_smoothing,
-                                    )
-                                })?;
-
-                            let texture_atlas =
-                                texture_atlases.get(&atlas_info.texture_atlas).unwrap();
-                            let location = atlas_info.location;
-                            let glyph_rect = texture_atlas.textures[location.glyph_index];
-                            let left = location.offset.x as f32;
-                            let top = location.offset.y as f32;
-                            let glyph_size = UVec2::new(glyph_rect.width(), glyph_rect.height());
-
-                            // offset by half the size because the origin is center
-                            let x = glyph_size.x as f32 / 2.0 + left + physical_glyph.x as f32;
-                            let y = line_y.round() + physical_glyph.y as f32 - top
-                                + glyph_size.y as f32 / 2.0;
-                            let y = match y_axis_orientation {
-                                YAxisOrientation::TopToBottom => y,
-                                YAxisOrientation::BottomToTop => box_size.y - y,
+                            let (atlas_info, position, glyph_size) = place_glyph(
+                                layout_glyph,
+                                line_y,
+                                font_id,
+                                font_smoothing,
+                                &mut text_input_pipeline,
+                                &mut texture_atlases,
+                                &mut textures,
+                            )?;
+                            let position = match y_axis_orientation {
+                                YAxisOrientation::TopToBottom => position,
+                                YAxisOrientation::BottomToTop => {
+                                    Vec2::new(position.x, box_size.y - position.y)
+                                }
                             };
 
-                            let position = Vec2::new(x, y);
+                            if let Some(style) = span_styles.get(span_index) {
+                                let thickness = (layout_glyph.font_size / 14.).max(1.);
+                                for (enabled, y) in [
+                                    (style.underline, line_y + 0.1 * layout_glyph.font_size),
+                                    (style.strikethrough, line_y - 0.3 * layout_glyph.font_size),
+                                ] {
+                                    if !enabled {
+                                        continue;
+                                    }
+                                    let x0 = layout_glyph.x;
+                                    let x1 = x0 + layout_glyph.w;
+                                    let y0 = y - 0.5 * thickness;
+                                    match decoration_rects.last_mut() {
+                                        // extend the line under the previous glyph
+                                        Some((rect, last_span))
+                                            if *last_span == span_index
+                                                && rect.min.y == y0
+                                                && (rect.max.x - x0).abs() < 0.5 =>
+                                        {
+                                            rect.max.x = x1;
+                                        }
+                                        _ => decoration_rects.push((
+                                            Rect::new(x0, y0, x1, y0 + thickness),
+                                            span_index,
+                                        )),
+                                    }
+                                }
+                            }
+
+                            let (byte_index, byte_end) = match display_text {
+                                Some(display_text) => (
+                                    display_text
+                                        .buffer_cursor(
+                                            buffer,
+                                            Cursor::new(line_i, layout_glyph.start),
+                                        )
+                                        .index,
+                                    display_text
+                                        .buffer_cursor(
+                                            buffer,
+                                            Cursor::new(line_i, layout_glyph.end),
+                                        )
+                                        .index,
+                                ),
+                                None => (layout_glyph.start, layout_glyph.end),
+                            };
 
                             let pos_glyph = TextInputGlyph {
                                 position,
                                 size: glyph_size.as_vec2(),
                                 atlas_info,
                                 span_index,
-                                byte_index: layout_glyph.start,
-                                byte_length: layout_glyph.end - layout_glyph.start,
+                                byte_index,
+                                byte_length: byte_end - byte_index,
                                 line_index: line_i,
                             };
                             layout_info.glyphs.push(pos_glyph);
                             Ok(())
-                        });
-
-                    result
+                        })
                 });
 
                 // Check result.
                 result?;
 
+                if let Some((gutter, style, gutter_layout)) = gutter.as_mut() {
+                    update_gutter_layout(
+                        gutter,
+                        gutter_layout,
+                        style.padding * scale_factor,
+                        buffer,
+                        layout_buffer,
+                        display_text,
+                        text_font.font.id(),
+                        text_font.font_smoothing,
+                        &mut text_input_pipeline,
+                        &mut texture_atlases,
+                        &mut textures,
+                    )?;
+                }
+
                 layout_info.size = box_size;
                 Ok(())
             });
@@ -318,8 +721,8 @@ pub fn text_input_system(
                     panic!("Fatal error when processing text: {e}.");
                 }
                 Ok(()) => {
-                    layout_info.size.x = layout_info.size.x * node.inverse_scale_factor();
-                    layout_info.size.y = layout_info.size.y * node.inverse_scale_factor();
+                    layout_info.size.x *= node.inverse_scale_factor();
+                    layout_info.size.y *= node.inverse_scale_factor();
                     editor.set_redraw(false);
                 }
             }
@@ -327,6 +730,82 @@ pub fn text_input_system(
     }
 }
 
+/// Lays out a `DisplayText` and scrolls it horizontally to keep the cursor visible
+fn update_display_text(
+    display_text: &mut DisplayText,
+    buffer: &Buffer,
+    cursor: Cursor,
+    font_system: &mut cosmic_text::FontSystem,
+) {
+    let text = display_text.text(buffer);
+    let (width, height) = buffer.size();
+    let attrs = buffer
+        .lines
+        .first()
+        .map(|line| line.attrs_list().defaults())
+        .unwrap_or_else(cosmic_text::Attrs::new);
+    let align = buffer.lines.first().and_then(|line| line.align());
+
+    // Masked text keeps its own horizontal scroll, as its glyphs have different widths to the buffer's
+    let scroll = match display_text.kind {
+        DisplayTextKind::Masked { .. } => display_text.buffer.scroll(),
+        DisplayTextKind::Preedit { .. } => buffer.scroll(),
+    };
+    let wrap = match display_text.kind {
+        DisplayTextKind::Masked { .. } => cosmic_text::Wrap::None,
+        DisplayTextKind::Preedit { .. } => buffer.wrap(),
+    };
+
+    let display_buffer = &mut display_text.buffer;
+    display_buffer.set_metrics_and_size(font_system, buffer.metrics(), width, height);
+    display_buffer.set_wrap(font_system, wrap);
+    display_buffer.set_text(font_system, &text, attrs, cosmic_text::Shaping::Advanced);
+    if let DisplayTextKind::Preedit { preedit, position } = &display_text.kind {
+        // Keep the styling of the text around the composition
+        for (line_i, (display_line, line)) in display_buffer
+            .lines
+            .iter_mut()
+            .zip(buffer.lines.iter())
+            .enumerate()
+        {
+            let mut attrs_list = line.attrs_list().clone();
+            if line_i == position.line {
+                let after = attrs_list.split_off(position.index);
+                let shift = position.index + preedit.text.len();
+                for (range, span) in after.spans_iter() {
+                    attrs_list.add_span(range.start + shift..range.end + shift, span.as_attrs());
+                }
+            }
+            display_line.set_attrs_list(attrs_list);
+        }
+    }
+    for buffer_line in display_buffer.lines.iter_mut() {
+        buffer_line.set_align(align);
+    }
+    display_buffer.set_scroll(scroll);
+    display_buffer.shape_until_scroll(font_system, false);
+
+    let Some(width) = width else {
+        return;
+    };
+    if let Some((x, _)) = display_text
+        .display_caret(buffer, cursor)
+        .and_then(|cursor| display_text.cursor_position(cursor))
+    {
+        let x = x as f32;
+        let line_width = buffer_dimensions(&display_text.buffer).x;
+        let mut scroll = display_text.buffer.scroll();
+        if x < scroll.horizontal {
+            scroll.horizontal = x;
+        } else if scroll.horizontal + width < x {
+            scroll.horizontal = x - width;
+        }
+        scroll.horizontal = scroll.horizontal.min((line_width - width).max(0.));
+        display_text.buffer.set_scroll(scroll);
+    }
+}
+
+#[allow(clippy::type_complexity)]
 pub fn text_input_prompt_system(
     mut textures: ResMut<Assets<Image>>,
     fonts: Res<Assets<Font>>,
@@ -339,19 +818,25 @@ pub fn text_input_prompt_system(
         &mut TextInputBuffer,
         Ref<TextInputNode>,
         Ref<TextInputPrompt>,
+        Option<&TextInputGutterLayout>,
     )>,
 ) {
-    for (node, text_font, text_input_layout_info, mut editor, input, prompt) in
+    for (node, text_font, text_input_layout_info, mut editor, input, prompt, gutter) in
         text_query.iter_mut()
     {
         let layout_info = text_input_layout_info.into_inner();
         let y_axis_orientation = YAxisOrientation::TopToBottom;
+        let width = (node.size().x - gutter.map_or(0., |gutter| gutter.width)).max(0.);
         if prompt.is_changed()
             || input.is_changed()
             || editor.prompt_buffer.is_none()
             || layout_info.glyphs.is_empty()
             || text_font.is_changed() && prompt.font.is_none()
             || node.is_changed()
+            || editor
+                .prompt_buffer
+                .as_ref()
+                .is_some_and(|buffer| buffer.size().0 != Some(width))
         {
             layout_info.glyphs.clear();
 
@@ -391,11 +876,12 @@ pub fn text_input_prompt_system(
 
             let linebreak = LineBreak::WordBoundary;
             let bounds = TextBounds {
-                width: Some(node.size().x),
+                width: Some(width),
                 height: Some(node.size().y),
             };
 
-            let face_info = load_font_to_fontdb(&font, font_system, map_handle_to_font_id, &fonts);
+            let face_info =
+                load_font_to_fontdb(&font.font, font_system, map_handle_to_font_id, &fonts);
 
             buffer.set_size(font_system, bounds.width, bounds.height);
 
@@ -433,8 +919,7 @@ pub fn text_input_prompt_system(
 
             let box_size = buffer_dimensions(buffer);
             let result = buffer.layout_runs().try_for_each(|run| {
-                let result = run
-                    .glyphs
+                run.glyphs
                     .iter()
                     .map(move |layout_glyph| (layout_glyph, run.line_y, run.line_i))
                     .try_for_each(|(layout_glyph, line_y, line_i)| {
@@ -513,9 +998,7 @@ pub fn text_input_prompt_system(
                         };
                         layout_info.glyphs.push(pos_glyph);
                         Ok(())
-                    });
-
-                result
+                    })
             });
 
             layout_info.size = box_size;
@@ -529,8 +1012,8 @@ pub fn text_input_prompt_system(
                     panic!("Fatal error when processing text: {e}.");
                 }
                 Ok(()) => {
-                    layout_info.size.x = layout_info.size.x * node.inverse_scale_factor();
-                    layout_info.size.y = layout_info.size.y * node.inverse_scale_factor();
+                    layout_info.size.x *= node.inverse_scale_factor();
+                    layout_info.size.y *= node.inverse_scale_factor();
                 }
             }
         }